@@ -5,12 +5,20 @@ use std::fmt;
 use crate::{direction::Direction, error::ParseActionError};
 
 /// Represents an action.
+///
+/// In LURD format, moves are lowercase letters and pushes are uppercase
+/// letters. Pulls are the uppercase compass letters `N`, `S`, `W` and `E`.
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub enum Action {
     /// Move action in a specified direction.
     Move(Direction),
     /// Push action in a specified direction.
     Push(Direction),
+    /// Pull action in a specified direction.
+    ///
+    /// The player moves in the direction and the box behind the player
+    /// follows.
+    Pull(Direction),
 }
 
 impl Action {
@@ -29,6 +37,7 @@ impl Action {
         match *self {
             Action::Move(direction) => direction,
             Action::Push(direction) => direction,
+            Action::Pull(direction) => direction,
         }
     }
 
@@ -61,12 +70,37 @@ impl Action {
     pub fn is_push(&self) -> bool {
         matches!(&self, Action::Push(_))
     }
+
+    /// Checks if the action is a pull action.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use soukoban::direction::Direction;
+    /// use soukoban::Action;
+    ///
+    /// let action = Action::Pull(Direction::Up);
+    /// assert!(action.is_pull());
+    /// ```
+    pub fn is_pull(&self) -> bool {
+        matches!(&self, Action::Pull(_))
+    }
 }
 
 impl TryFrom<char> for Action {
     type Error = ParseActionError;
 
     fn try_from(char: char) -> Result<Self, ParseActionError> {
+        let pull_direction = match char {
+            'N' => Some(Direction::Up),
+            'S' => Some(Direction::Down),
+            'W' => Some(Direction::Left),
+            'E' => Some(Direction::Right),
+            _ => None,
+        };
+        if let Some(direction) = pull_direction {
+            return Ok(Action::Pull(direction));
+        }
         let direction = match char.to_ascii_lowercase() {
             'u' => Direction::Up,
             'd' => Direction::Down,
@@ -84,6 +118,14 @@ impl TryFrom<char> for Action {
 
 impl From<Action> for char {
     fn from(action: Action) -> Self {
+        if action.is_pull() {
+            return match action.direction() {
+                Direction::Up => 'N',
+                Direction::Down => 'S',
+                Direction::Left => 'W',
+                Direction::Right => 'E',
+            };
+        }
         let char = match action.direction() {
             Direction::Up => 'u',
             Direction::Down => 'd',
@@ -8,9 +8,12 @@ use std::{
 
 use nalgebra::Vector2;
 
-use crate::{action::Action, error::ParseActionsError, run_length::rle_decode};
+use crate::{
+    action::Action, direction::Direction, error::ParseActionsError, run_length::rle_decode,
+};
 
 /// Secondary statistics for a sequence of actions.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub struct SecondaryValues {
     /// Straight line box pushes.
     pub box_lines: i32,
@@ -42,6 +45,34 @@ impl Actions {
         self.iter().filter(|action| action.is_push()).count()
     }
 
+    /// Returns the number of pulls.
+    pub fn pulls(&self) -> usize {
+        self.iter().filter(|action| action.is_pull()).count()
+    }
+
+    /// Rotates the actions 90° in the same way as [`Map::rotate`].
+    ///
+    /// If the actions are a solution of a map, the rotated actions are a
+    /// solution of the rotated map.
+    ///
+    /// [`Map::rotate`]: crate::Map::rotate
+    pub fn rotate(&mut self) {
+        self.transform(|direction| -direction.rotate());
+    }
+
+    /// Flips the actions horizontally in the same way as [`Map::flip`].
+    ///
+    /// If the actions are a solution of a map, the flipped actions are a
+    /// solution of the flipped map.
+    ///
+    /// [`Map::flip`]: crate::Map::flip
+    pub fn flip(&mut self) {
+        self.transform(|direction| match direction {
+            Direction::Left | Direction::Right => direction.flip(),
+            Direction::Up | Direction::Down => direction,
+        });
+    }
+
     /// Returns the secondary values.
     pub fn secondary_values(&self) -> SecondaryValues {
         let mut box_lines = 0;
@@ -87,6 +118,18 @@ impl Actions {
             player_lines,
         }
     }
+
+    /// Transforms the directions of the actions based on the provided
+    /// operation.
+    fn transform(&mut self, operation: impl Fn(Direction) -> Direction) {
+        for action in self.iter_mut() {
+            *action = match *action {
+                Action::Move(direction) => Action::Move(operation(direction)),
+                Action::Push(direction) => Action::Push(operation(direction)),
+                Action::Pull(direction) => Action::Pull(operation(direction)),
+            };
+        }
+    }
 }
 
 impl FromStr for Actions {
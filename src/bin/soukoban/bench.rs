@@ -0,0 +1,159 @@
+//! Benchmarking the solver over level collections.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use soukoban::{
+    solver::{Solver, Strategy},
+    Level,
+};
+
+/// The result of solving a level.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LevelResult {
+    /// The 0-based index of the level in the collection.
+    pub index: usize,
+    /// The name of the level.
+    pub name: String,
+    /// Whether the level is solved within the budget.
+    pub solved: bool,
+    /// The number of nodes expanded by the solver.
+    pub expanded_nodes: usize,
+    /// The time spent by the solver in milliseconds.
+    pub time_ms: f64,
+    /// The number of moves of the solution.
+    pub moves: Option<usize>,
+    /// The number of pushes of the solution.
+    pub pushes: Option<usize>,
+}
+
+/// The results of a benchmark run.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Report {
+    /// The results of each level.
+    pub levels: Vec<LevelResult>,
+}
+
+/// The budget of the solver for each level.
+#[derive(Clone, Copy, Debug)]
+pub struct Budget {
+    /// The maximum number of expanded nodes.
+    pub node_limit: Option<usize>,
+    /// The maximum duration.
+    pub time_limit: Option<Duration>,
+}
+
+/// Solves every level with the budget.
+pub fn run(
+    levels: &[Level],
+    strategy: Strategy,
+    budget: Budget,
+    mut name: impl FnMut(&Level, usize) -> String,
+) -> Report {
+    let levels = levels
+        .iter()
+        .enumerate()
+        .map(|(index, level)| {
+            let mut solver = Solver::new(level.map().clone(), strategy);
+            solver.set_node_limit(budget.node_limit);
+            solver.set_time_limit(budget.time_limit);
+            let start = Instant::now();
+            let solution = solver.a_star_search().ok();
+            LevelResult {
+                index,
+                name: name(level, index),
+                solved: solution.is_some(),
+                expanded_nodes: solver.expanded_nodes(),
+                time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                moves: solution.as_ref().map(|solution| solution.moves()),
+                pushes: solution.as_ref().map(|solution| solution.pushes()),
+            }
+        })
+        .collect();
+    Report { levels }
+}
+
+impl Report {
+    /// Returns the report as a table.
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:<24} {:>6} {:>10} {:>10} {:>7} {:>7}\n",
+            "level", "solved", "nodes", "time (ms)", "moves", "pushes"
+        );
+        for result in &self.levels {
+            table += &format!(
+                "{:<24} {:>6} {:>10} {:>10.1} {:>7} {:>7}\n",
+                result.name,
+                if result.solved { "yes" } else { "no" },
+                result.expanded_nodes,
+                result.time_ms,
+                optional(result.moves),
+                optional(result.pushes)
+            );
+        }
+        table += &format!(
+            "solved {}/{}, {} nodes, {:.1} ms\n",
+            self.levels.iter().filter(|result| result.solved).count(),
+            self.levels.len(),
+            self.levels
+                .iter()
+                .map(|result| result.expanded_nodes)
+                .sum::<usize>(),
+            self.levels.iter().map(|result| result.time_ms).sum::<f64>()
+        );
+        table
+    }
+
+    /// Compares the report with a baseline and returns the regressions.
+    ///
+    /// Levels are matched by their index, since names may be duplicated.
+    ///
+    /// A level regresses if it is no longer solved, if its solution becomes
+    /// longer, or if the number of expanded nodes grows by more than
+    /// `tolerance` (relative). Times are not compared, since they depend on the
+    /// machine.
+    pub fn regressions(&self, baseline: &Report, tolerance: f64) -> Vec<String> {
+        let baseline: HashMap<_, _> = baseline
+            .levels
+            .iter()
+            .map(|result| (result.index, result))
+            .collect();
+        let mut regressions = Vec::new();
+        for result in &self.levels {
+            let Some(base) = baseline.get(&result.index) else {
+                continue;
+            };
+            if !base.solved {
+                continue;
+            }
+            if !result.solved {
+                regressions.push(format!("{}: no longer solved", result.name));
+                continue;
+            }
+            if result.moves > base.moves || result.pushes > base.pushes {
+                regressions.push(format!(
+                    "{}: solution grew from {}/{} to {}/{} (moves/pushes)",
+                    result.name,
+                    optional(base.moves),
+                    optional(base.pushes),
+                    optional(result.moves),
+                    optional(result.pushes)
+                ));
+            }
+            if result.expanded_nodes as f64 > base.expanded_nodes as f64 * (1.0 + tolerance) {
+                regressions.push(format!(
+                    "{}: expanded nodes grew from {} to {}",
+                    result.name, base.expanded_nodes, result.expanded_nodes
+                ));
+            }
+        }
+        regressions
+    }
+}
+
+fn optional(value: Option<usize>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
@@ -0,0 +1,298 @@
+//! Reading and writing level collections in different formats.
+
+use std::{error::Error, fmt::Write, mem, path::Path, str::FromStr};
+
+use clap::ValueEnum;
+use soukoban::{run_length::rle_encode, Level, Map, ParseLevelError};
+
+use crate::SOLUTION_KEY;
+
+/// The format of a level collection.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, ValueEnum)]
+pub enum Format {
+    /// XSB format
+    Xsb,
+    /// XSB format with run-length encoded maps
+    Rle,
+    /// SOK format
+    Sok,
+    /// SLC format (XML)
+    Slc,
+}
+
+impl Format {
+    /// Guesses the format from the extension of the path, defaults to XSB.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("rle") => Self::Rle,
+            Some("sok") => Self::Sok,
+            Some("slc") => Self::Slc,
+            _ => Self::Xsb,
+        }
+    }
+}
+
+/// A level collection.
+#[derive(Default)]
+pub struct Collection {
+    /// The levels of the collection.
+    pub entries: Vec<Entry>,
+    /// The text after the last level.
+    pub trailer: String,
+}
+
+/// A level of a collection.
+pub struct Entry {
+    /// The text before the level which is not part of any level, such as the
+    /// collection header or level labels.
+    pub preamble: String,
+    /// The level.
+    pub level: Level,
+}
+
+impl Collection {
+    /// Returns an iterator over the levels.
+    pub fn levels(&self) -> impl Iterator<Item = &Level> {
+        self.entries.iter().map(|entry| &entry.level)
+    }
+
+    /// Returns a mutable iterator over the levels.
+    pub fn levels_mut(&mut self) -> impl Iterator<Item = &mut Level> {
+        self.entries.iter_mut().map(|entry| &mut entry.level)
+    }
+}
+
+/// Parses a collection.
+///
+/// The text which is not part of any level is kept, except for SLC format.
+pub fn read(str: &str, format: Format) -> Result<Collection, Box<dyn Error>> {
+    if format == Format::Slc {
+        return read_slc(str);
+    }
+
+    let mut collection = Collection::default();
+    let mut group = String::new();
+    let mut in_block_comment = false;
+    // Split the text into groups by empty lines, like `Level::load_from_str`
+    for line in str.split_inclusive('\n') {
+        let trimmed_line = line.trim().to_lowercase();
+        if in_block_comment {
+            in_block_comment = !trimmed_line.starts_with("comment-end");
+        } else if let Some(comment) = trimmed_line.strip_prefix("comment:") {
+            in_block_comment = comment.trim_start().is_empty();
+        }
+        group += line;
+        if !in_block_comment && trimmed_line.is_empty() {
+            push_group(&mut collection, &group, format)?;
+            group.clear();
+        }
+    }
+    push_group(&mut collection, &group, format)?;
+    Ok(collection)
+}
+
+/// Appends a group of lines to the collection, as a level if it contains map
+/// data, or as text otherwise.
+fn push_group(
+    collection: &mut Collection,
+    group: &str,
+    format: Format,
+) -> Result<(), ParseLevelError> {
+    match Level::from_str(group) {
+        Ok(mut level) => {
+            if format == Format::Sok {
+                if let Some(title) = sok_title(group) {
+                    level
+                        .metadata_mut()
+                        .entry("title".to_string())
+                        .or_insert(title);
+                }
+            }
+            collection.entries.push(Entry {
+                preamble: mem::take(&mut collection.trailer),
+                level,
+            })
+        }
+        Err(ParseLevelError::NoMap) => collection.trailer += group,
+        Err(error) => return Err(error),
+    }
+    Ok(())
+}
+
+/// Returns the title of a level in SOK format, which is the last line before
+/// the map that is neither a comment nor metadata.
+fn sok_title(group: &str) -> Option<String> {
+    let mut title = None;
+    for line in group.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') || line.contains(':') {
+            continue;
+        }
+        if line
+            .chars()
+            .all(|char| " -_#$.@*+|".contains(char) || char.is_ascii_digit())
+        {
+            break;
+        }
+        title = Some(line.to_string());
+    }
+    title
+}
+
+/// Serializes a collection.
+pub fn write(collection: &Collection, format: Format) -> String {
+    let write_level = match format {
+        Format::Xsb => write_xsb_level,
+        Format::Rle => write_rle_level,
+        Format::Sok => write_sok_level,
+        Format::Slc => return write_slc(collection),
+    };
+    let mut result = String::new();
+    for entry in &collection.entries {
+        result += &entry.preamble;
+        result += &write_level(&entry.level);
+        result.push('\n');
+    }
+    result + &collection.trailer
+}
+
+/// Returns the rows of the map, using spaces for floors and empty tiles.
+fn rows(map: &Map) -> Vec<String> {
+    map.to_string()
+        .lines()
+        .map(|row| row.replace(['_', '-'], " ").trim_end().to_string())
+        .collect()
+}
+
+/// Returns the metadata of the level as written by the `Display` of `Level`.
+fn metadata(level: &Level) -> String {
+    level.to_string()[level.map().to_string().len()..].to_string()
+}
+
+fn write_xsb_level(level: &Level) -> String {
+    let rows: String = rows(level.map())
+        .iter()
+        .map(|row| row.to_string() + "\n")
+        .collect();
+    rows + &metadata(level)
+}
+
+fn write_rle_level(level: &Level) -> String {
+    let rows: Vec<_> = rows(level.map())
+        .iter()
+        .map(|row| rle_encode(&row.replace(' ', "-")).unwrap())
+        .collect();
+    rows.join("|") + "\n" + &metadata(level)
+}
+
+fn write_sok_level(level: &Level) -> String {
+    let mut level = level.clone();
+    let mut result = String::new();
+    // The title is written before the map instead of in the metadata
+    if let Some(title) = level.metadata_mut().remove("title") {
+        writeln!(result, "{title}").unwrap();
+    }
+    result + &write_xsb_level(&level)
+}
+
+fn write_slc(collection: &Collection) -> String {
+    let max_width = collection
+        .levels()
+        .map(|level| level.map().dimensions().x)
+        .max()
+        .unwrap_or(0);
+    let max_height = collection
+        .levels()
+        .map(|level| level.map().dimensions().y)
+        .max()
+        .unwrap_or(0);
+
+    let mut result = String::new();
+    writeln!(result, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(result, "<SokobanLevels>").unwrap();
+    writeln!(
+        result,
+        r#"  <LevelCollection MaxWidth="{max_width}" MaxHeight="{max_height}">"#
+    )
+    .unwrap();
+    for (index, level) in collection.levels().enumerate() {
+        let id = level
+            .metadata()
+            .get("title")
+            .cloned()
+            .unwrap_or_else(|| (index + 1).to_string());
+        let dimensions = level.map().dimensions();
+        write!(
+            result,
+            r#"    <Level Id="{}" Width="{}" Height="{}""#,
+            escape_xml(&id),
+            dimensions.x,
+            dimensions.y
+        )
+        .unwrap();
+        if let Some(author) = level.metadata().get("author") {
+            write!(result, r#" Copyright="{}""#, escape_xml(author)).unwrap();
+        }
+        writeln!(result, ">").unwrap();
+        for row in rows(level.map()) {
+            writeln!(result, "      <L>{row}</L>").unwrap();
+        }
+        if let Some(solution) = level.metadata().get(SOLUTION_KEY) {
+            writeln!(result, "      <Solution>{solution}</Solution>").unwrap();
+        }
+        writeln!(result, "    </Level>").unwrap();
+    }
+    writeln!(result, "  </LevelCollection>").unwrap();
+    writeln!(result, "</SokobanLevels>").unwrap();
+    result
+}
+
+fn read_slc(str: &str) -> Result<Collection, Box<dyn Error>> {
+    let document = roxmltree::Document::parse(str)?;
+    let mut collection = Collection::default();
+    for element in document
+        .descendants()
+        .filter(|node| node.has_tag_name("Level"))
+    {
+        let mut rows = Vec::new();
+        let mut solution = None;
+        for child in element.children().filter(roxmltree::Node::is_element) {
+            let text = child.text().unwrap_or_default();
+            match child.tag_name().name() {
+                "L" => rows.push(text),
+                "Solution" => solution = Some(text.trim()),
+                name => return Err(format!("unexpected {name} element in Level").into()),
+            }
+        }
+
+        let mut level = Level::from_map(Map::from_str(&rows.join("\n"))?);
+        for (attribute, key) in [("Id", "title"), ("Copyright", "author")] {
+            if let Some(value) = element.attribute(attribute) {
+                level
+                    .metadata_mut()
+                    .insert(key.to_string(), value.to_string());
+            }
+        }
+        if let Some(solution) = solution {
+            level
+                .metadata_mut()
+                .insert(SOLUTION_KEY.to_string(), solution.to_string());
+        }
+        collection.entries.push(Entry {
+            preamble: String::new(),
+            level,
+        });
+    }
+    Ok(collection)
+}
+
+fn escape_xml(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,347 @@
+//! A command-line tool for Sokoban level collections.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use soukoban::{
+    solver::{Solver, Strategy},
+    Actions, Level, Map,
+};
+
+mod bench;
+mod format;
+use format::{Collection, Format};
+
+/// The metadata key of the solutions.
+const SOLUTION_KEY: &str = "solution";
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solves the levels and writes the collection with the solutions
+    Solve {
+        /// The collection file
+        file: PathBuf,
+        /// The solver strategy
+        #[arg(short, long, value_enum, default_value_t = StrategyArg::Fast)]
+        strategy: StrategyArg,
+        /// The time limit for each level in seconds
+        #[arg(short, long, default_value = "10", value_parser = parse_time_limit)]
+        time_limit: Duration,
+        /// The output collection file, the input file by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Verifies the solutions embedded in the collection
+    Verify {
+        /// The collection file
+        file: PathBuf,
+    },
+    /// Converts the collection to another format
+    Convert {
+        /// The input collection file
+        input: PathBuf,
+        /// The output collection file
+        output: PathBuf,
+        /// The format of the input file, guessed from the extension by default
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        /// The format of the output file, guessed from the extension by default
+        #[arg(long, value_enum)]
+        to: Option<Format>,
+    },
+    /// Normalizes the maps of the collection
+    ///
+    /// The solutions of changed levels are removed, since the normalized map
+    /// may be rotated or flipped.
+    Normalize {
+        /// The collection file
+        file: PathBuf,
+        /// The output collection file, the input file by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Removes duplicate levels from the collection
+    Dedupe {
+        /// The collection file
+        file: PathBuf,
+        /// The output collection file, the input file by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Prints the metrics of the solutions embedded in the collection
+    Stats {
+        /// The collection file
+        file: PathBuf,
+    },
+    /// Runs the solver over every level of the collection
+    Bench {
+        /// The collection file
+        file: PathBuf,
+        /// The solver strategy
+        #[arg(short, long, value_enum, default_value_t = StrategyArg::Fast)]
+        strategy: StrategyArg,
+        /// The maximum number of expanded nodes for each level
+        #[arg(short, long, default_value_t = 100_000)]
+        node_limit: usize,
+        /// The time limit for each level in seconds
+        #[arg(short, long, value_parser = parse_time_limit)]
+        time_limit: Option<Duration>,
+        /// Writes the results as JSON to this file
+        #[arg(long)]
+        json: Option<PathBuf>,
+        /// Compares the results with a baseline JSON file
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+        /// The allowed relative growth of expanded nodes compared to the
+        /// baseline
+        #[arg(long, default_value_t = 0.1)]
+        tolerance: f64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyArg {
+    Fast,
+    OptimalPush,
+    OptimalMove,
+}
+
+impl From<StrategyArg> for Strategy {
+    fn from(strategy: StrategyArg) -> Self {
+        match strategy {
+            StrategyArg::Fast => Strategy::Fast,
+            StrategyArg::OptimalPush => Strategy::OptimalPush,
+            StrategyArg::OptimalMove => Strategy::OptimalMove,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the command, returns whether all levels succeeded.
+fn run(command: Command) -> Result<bool, Box<dyn Error>> {
+    match command {
+        Command::Solve {
+            file,
+            strategy,
+            time_limit,
+            output,
+        } => {
+            let mut collection = read_collection(&file, None)?;
+            let mut success = true;
+            for (index, level) in collection.levels_mut().enumerate() {
+                let mut solver = Solver::new(level.map().clone(), strategy.into());
+                solver.set_time_limit(Some(time_limit));
+                match solver.a_star_search() {
+                    Ok(solution) => {
+                        println!(
+                            "{}: solved ({} moves, {} pushes)",
+                            level_name(level, index),
+                            solution.moves(),
+                            solution.pushes()
+                        );
+                        level
+                            .metadata_mut()
+                            .insert(SOLUTION_KEY.to_string(), solution.to_string());
+                    }
+                    Err(error) => {
+                        println!("{}: {error}", level_name(level, index));
+                        success = false;
+                    }
+                }
+            }
+            write_collection(output.as_ref().unwrap_or(&file), &collection, None)?;
+            Ok(success)
+        }
+        Command::Verify { file } => {
+            let mut success = true;
+            for (index, level) in read_collection(&file, None)?.levels().enumerate() {
+                let name = level_name(level, index);
+                let Some(solution) = level.metadata().get(SOLUTION_KEY) else {
+                    println!("{name}: no solution");
+                    success = false;
+                    continue;
+                };
+                let result = Actions::from_str(solution)
+                    .map_err(|error| error.to_string())
+                    .and_then(|actions| {
+                        level
+                            .map()
+                            .verify(&actions)
+                            .map_err(|error| error.to_string())
+                    });
+                match result {
+                    Ok(_) => println!("{name}: ok"),
+                    Err(error) => {
+                        println!("{name}: {error}");
+                        success = false;
+                    }
+                }
+            }
+            Ok(success)
+        }
+        Command::Convert {
+            input,
+            output,
+            from,
+            to,
+        } => {
+            let collection = read_collection(&input, from)?;
+            write_collection(&output, &collection, to)?;
+            Ok(true)
+        }
+        Command::Normalize { file, output } => {
+            let mut collection = read_collection(&file, None)?;
+            for level in collection.levels_mut() {
+                let map = level.map().clone();
+                level.map_mut().normalize();
+                if *level.map() != map {
+                    level.metadata_mut().remove(SOLUTION_KEY);
+                }
+            }
+            write_collection(output.as_ref().unwrap_or(&file), &collection, None)?;
+            Ok(true)
+        }
+        Command::Dedupe { file, output } => {
+            let mut collection = read_collection(&file, None)?;
+            let count = collection.entries.len();
+            // The fingerprints are only used as bucket keys, since different
+            // maps may share a fingerprint
+            let mut canonical_forms: HashMap<u64, Vec<Map>> = HashMap::new();
+            collection.entries.retain(|entry| {
+                let map = entry.level.map();
+                let bucket = canonical_forms.entry(map.fingerprint()).or_default();
+                let canonical_form = map.canonical_form();
+                if bucket.contains(&canonical_form) {
+                    return false;
+                }
+                bucket.push(canonical_form);
+                true
+            });
+            println!(
+                "removed {} duplicate levels",
+                count - collection.entries.len()
+            );
+            write_collection(output.as_ref().unwrap_or(&file), &collection, None)?;
+            Ok(true)
+        }
+        Command::Stats { file } => {
+            println!(
+                "level\tmoves\tpushes\tbox lines\tbox changes\tpushing sessions\tplayer lines"
+            );
+            let mut success = true;
+            for (index, level) in read_collection(&file, None)?.levels().enumerate() {
+                let name = level_name(level, index);
+                let Some(solution) = level.metadata().get(SOLUTION_KEY) else {
+                    continue;
+                };
+                let solution = match Actions::from_str(solution) {
+                    Ok(solution) => solution,
+                    Err(error) => {
+                        eprintln!("{name}: {error}");
+                        success = false;
+                        continue;
+                    }
+                };
+                let values = solution.secondary_values();
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    name,
+                    solution.moves(),
+                    solution.pushes(),
+                    values.box_lines,
+                    values.box_changes,
+                    values.pushing_sessions,
+                    values.player_lines
+                );
+            }
+            Ok(success)
+        }
+        Command::Bench {
+            file,
+            strategy,
+            node_limit,
+            time_limit,
+            json,
+            baseline,
+            tolerance,
+        } => {
+            let levels: Vec<_> = read_collection(&file, None)?.levels().cloned().collect();
+            let budget = bench::Budget {
+                node_limit: Some(node_limit),
+                time_limit,
+            };
+            let report = bench::run(&levels, strategy.into(), budget, level_name);
+            print!("{}", report.table());
+            if let Some(json) = json {
+                fs::write(json, serde_json::to_string_pretty(&report)?)?;
+            }
+            let Some(baseline) = baseline else {
+                return Ok(true);
+            };
+            let baseline: bench::Report = serde_json::from_str(&fs::read_to_string(baseline)?)?;
+            let regressions = report.regressions(&baseline, tolerance);
+            for regression in &regressions {
+                println!("regression: {regression}");
+            }
+            Ok(regressions.is_empty())
+        }
+    }
+}
+
+/// Parses a time limit in seconds, which must be positive and finite.
+fn parse_time_limit(str: &str) -> Result<Duration, String> {
+    let seconds = str.parse::<f64>().map_err(|error| error.to_string())?;
+    if seconds <= 0.0 {
+        return Err("time limit must be positive".to_string());
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
+}
+
+fn read_collection(path: &Path, format: Option<Format>) -> Result<Collection, Box<dyn Error>> {
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+    format::read(&fs::read_to_string(path)?, format)
+}
+
+fn write_collection(
+    path: &Path,
+    collection: &Collection,
+    format: Option<Format>,
+) -> Result<(), Box<dyn Error>> {
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+    fs::write(path, format::write(collection, format))?;
+    Ok(())
+}
+
+/// Returns the title of the level, or its 1-based index if it has no title.
+fn level_name(level: &Level, index: usize) -> String {
+    level
+        .metadata()
+        .get("title")
+        .cloned()
+        .unwrap_or_else(|| format!("#{}", index + 1))
+}
@@ -4,7 +4,14 @@ use std::collections::{HashSet, VecDeque};
 
 use nalgebra::Vector2;
 
-use crate::{direction::Direction, map::Map, tiles::Tiles};
+use crate::{
+    direction::Direction,
+    map::Map,
+    path_finding::box_move_distances,
+    rules::MovementRules,
+    tiles::Tiles,
+    topology::{Grid, Square},
+};
 
 /// Checks if the given box position is a static deadlock.
 ///
@@ -51,6 +58,8 @@ pub fn is_static_deadlock(
 }
 
 /// Checks if the given box position is a freeze deadlock.
+///
+/// Freeze deadlocks only apply when boxes can not be pulled.
 pub fn is_freeze_deadlock(
     map: &Map,
     box_position: Vector2<i32>,
@@ -151,6 +160,60 @@ pub fn calculate_static_deadlocks(map: &Map) -> HashSet<Vector2<i32>> {
     dead_positions
 }
 
+/// Calculates static deadlock positions of a box with the ID under the
+/// movement rules.
+///
+/// A box is dead on the positions from which it can not be moved to any goal
+/// with the same ID, even without other boxes. When pulls are allowed, most
+/// corners are no longer dead. For push-only rules on maps without IDs, this is
+/// the same as [`calculate_static_deadlocks`].
+pub fn calculate_static_deadlocks_with_rules(
+    map: &Map,
+    rules: MovementRules,
+    id: u32,
+) -> HashSet<Vector2<i32>> {
+    let mut dead_positions = HashSet::new();
+    if rules == MovementRules::PushOnly {
+        dead_positions = calculate_static_deadlocks(map);
+        if !map.has_ids() {
+            return dead_positions;
+        }
+    }
+    let is_floor = |position| map.in_bounds(position) && map[position].intersects(Tiles::Floor);
+    let goal_positions = map
+        .goal_positions()
+        .iter()
+        .copied()
+        .filter(|&position| map.goal_id(position) == id);
+    let distances = box_move_distances::<Square>(goal_positions, rules, is_floor);
+    for y in 0..map.dimensions().y {
+        for x in 0..map.dimensions().x {
+            let position = Vector2::new(x, y);
+            if is_floor(position) && !distances.contains_key(&position) {
+                dead_positions.insert(position);
+            }
+        }
+    }
+    dead_positions
+}
+
+/// Calculates static deadlock positions of a map on any grid topology.
+///
+/// A box on any of the returned positions can not be pushed to a goal, even
+/// without other boxes and regardless of the player's position.
+pub fn calculate_static_deadlocks_in<M: Grid>(map: &M) -> HashSet<Vector2<i32>> {
+    let push_distances = box_move_distances::<M::Topology>(
+        map.goal_positions().iter().copied(),
+        MovementRules::PushOnly,
+        |position| map.is_floor(position),
+    );
+    let dimensions = map.dimensions();
+    (0..dimensions.y)
+        .flat_map(|y| (0..dimensions.x).map(move |x| Vector2::new(x, y)))
+        .filter(|&position| map.is_floor(position) && !push_distances.contains_key(&position))
+        .collect()
+}
+
 /// Calculate the positions of the useless floors.
 pub fn calculate_useless_floors(mut map: Map) -> HashSet<Vector2<i32>> {
     let mut useless_floors = HashSet::new();
@@ -0,0 +1,114 @@
+//! Difficulty estimation.
+
+use nalgebra::Vector2;
+
+use crate::{
+    actions::SecondaryValues,
+    deadlock::calculate_static_deadlocks,
+    map::Map,
+    solver::{Solver, Strategy},
+    tiles::Tiles,
+};
+
+/// The maximum number of nodes expanded by the solver.
+const NODE_LIMIT: usize = 2_000;
+
+/// The score added in place of the solution metrics when the solver fails to
+/// find a solution within the limit.
+const UNSOLVED_PENALTY: f64 = 50.0;
+
+/// The score per doubling of the number of nodes expanded by the solver.
+const EXPANDED_NODES_WEIGHT: f64 = 4.0;
+
+/// The score of a map whose floors are all static deadlock positions.
+const DEADLOCK_DENSITY_WEIGHT: f64 = 10.0;
+
+/// The score of a map whose floors are all occupied by boxes. Crowded maps
+/// leave little room to maneuver.
+const BOX_DENSITY_WEIGHT: f64 = 150.0;
+
+/// The score per push of the solution.
+const PUSH_WEIGHT: f64 = 0.2;
+
+/// The score per box change of the solution. Switching between boxes is
+/// weighted higher than pushing the same box.
+const BOX_CHANGE_WEIGHT: f64 = 0.5;
+
+/// The score per pushing session of the solution.
+const PUSHING_SESSION_WEIGHT: f64 = 0.2;
+
+/// A report of the estimated difficulty of a map.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DifficultyReport {
+    /// The number of nodes expanded by the solver.
+    pub expanded_nodes: usize,
+    /// The average number of successors of the expanded nodes.
+    pub branching_factor: f64,
+    /// The number of moves of the solution found by the solver, or `None` if
+    /// no solution is found within the limit.
+    pub moves: Option<usize>,
+    /// The number of pushes of the solution found by the solver, or `None` if
+    /// no solution is found within the limit.
+    pub pushes: Option<usize>,
+    /// The secondary values of the solution found by the solver, or `None` if
+    /// no solution is found within the limit.
+    pub secondary_values: Option<SecondaryValues>,
+    /// The number of static deadlock positions.
+    pub deadlock_squares: usize,
+    /// The estimated difficulty, a non-negative number, higher is harder.
+    pub score: f64,
+}
+
+/// Estimates the difficulty of the map.
+///
+/// The estimate combines the effort of the solver under a fixed node limit,
+/// the metrics of the solution found and the number of static deadlock
+/// positions. The branching factor and the number of boxes are added
+/// unweighted. The weights are calibrated so that Microban levels are on
+/// average easier than Sasquatch levels, which are on average easier than
+/// SokHard levels.
+pub fn estimate(map: &Map) -> DifficultyReport {
+    let mut solver = Solver::new(map.clone(), Strategy::Fast);
+    solver.set_node_limit(Some(NODE_LIMIT));
+    let solution = solver.a_star_search().ok();
+
+    let expanded_nodes = solver.expanded_nodes();
+    let branching_factor = if expanded_nodes == 0 {
+        0.0
+    } else {
+        solver.generated_nodes() as f64 / expanded_nodes as f64
+    };
+    let deadlock_squares = calculate_static_deadlocks(map).len();
+    let floors = (0..map.dimensions().y)
+        .flat_map(|y| (0..map.dimensions().x).map(move |x| Vector2::new(x, y)))
+        .filter(|&position| map[position].intersects(Tiles::Floor))
+        .count();
+
+    let boxes = map.box_positions().len() as f64;
+    let mut score = (expanded_nodes as f64 + 1.0).log2() * EXPANDED_NODES_WEIGHT
+        + branching_factor
+        + deadlock_squares as f64 / floors as f64 * DEADLOCK_DENSITY_WEIGHT
+        + boxes
+        + boxes / floors as f64 * BOX_DENSITY_WEIGHT;
+    match &solution {
+        Some(solution) => {
+            let secondary_values = solution.secondary_values();
+            score += solution.pushes() as f64 * PUSH_WEIGHT
+                + secondary_values.box_changes as f64 * BOX_CHANGE_WEIGHT
+                + secondary_values.pushing_sessions as f64 * PUSHING_SESSION_WEIGHT;
+        }
+        None => score += UNSOLVED_PENALTY,
+    }
+
+    DifficultyReport {
+        expanded_nodes,
+        branching_factor,
+        moves: solution.as_ref().map(|solution| solution.moves()),
+        pushes: solution.as_ref().map(|solution| solution.pushes()),
+        secondary_values: solution
+            .as_ref()
+            .map(|solution| solution.secondary_values()),
+        deadlock_squares,
+        score,
+    }
+}
@@ -1,5 +1,6 @@
 //! Error types.
 
+use nalgebra::Vector2;
 use thiserror::Error;
 
 /// An error which can be returned when parsing a level.
@@ -17,6 +18,9 @@ pub enum ParseLevelError {
     /// An error occurred while parsing the map.
     #[error(transparent)]
     ParseMapError(#[from] ParseMapError),
+    /// The Sokoban+ box or goal sequence is not a list of IDs.
+    #[error("invalid Sokoban+ sequence `{0}`")]
+    InvalidSequence(String),
 }
 
 /// An error which can be returned when parsing a map.
@@ -44,6 +48,9 @@ pub enum ParseMapError {
     /// Actions are invalid and cannot be used to create level.
     #[error("invalid actions")]
     InvalidActions,
+    /// A tile of a hexagonal map is not aligned to the grid.
+    #[error("misaligned tile at ({}, {})", .0.x, .0.y)]
+    MisalignedTile(Vector2<i32>),
 }
 
 /// An error which can be returned when parsing actions.
@@ -55,6 +62,9 @@ pub enum ParseActionsError {
     /// An error occurred during RLE decoding.
     #[error(transparent)]
     DecodeRleError(#[from] DecodeRleError),
+    /// The player marker of a multi-player actions is invalid.
+    #[error("invalid player marker")]
+    InvalidPlayerMarker,
 }
 
 /// An error which can be returned when parsing a action.
@@ -87,6 +97,54 @@ pub enum SearchError {
     /// No solution found.
     #[error("no solution found")]
     NoSolution,
+    /// The search limit was reached before a solution was found.
+    #[error("search limit exceeded")]
+    LimitExceeded,
+}
+
+/// An error which can be returned when creating a generator.
+#[derive(Error, Clone, Eq, PartialEq, Debug)]
+pub enum GeneratorError {
+    /// The dimensions are smaller than 3x3.
+    #[error("dimensions are too small")]
+    DimensionsTooSmall,
+    /// The box count is zero.
+    #[error("no boxes")]
+    NoBoxes,
+}
+
+/// An error which can be returned when verifying a solution.
+#[derive(Error, Clone, Eq, PartialEq, Debug)]
+pub enum VerifyError {
+    /// Movement of the action at the specified index is blocked.
+    #[error("movement of action {0} is blocked")]
+    MoveBlocked(usize),
+    /// Push of the action at the specified index is blocked.
+    #[error("push of action {0} is blocked")]
+    PushBlocked(usize),
+    /// The action at the specified index is declared as a push, but no box is
+    /// pushed.
+    #[error("action {0} is declared as a push but is a move")]
+    MoveDeclaredAsPush(usize),
+    /// The action at the specified index is declared as a move, but a box is
+    /// pushed.
+    #[error("action {0} is declared as a move but is a push")]
+    PushDeclaredAsMove(usize),
+    /// The action at the specified index is declared as a pull, but there is
+    /// no box behind the player.
+    #[error("action {0} is declared as a pull but no box is pulled")]
+    MoveDeclaredAsPull(usize),
+    /// The action at the specified index is a push, but pushes are not
+    /// allowed by the movement rules.
+    #[error("action {0} is a push but pushes are not allowed")]
+    PushNotAllowed(usize),
+    /// The action at the specified index is a pull, but pulls are not allowed
+    /// by the movement rules.
+    #[error("action {0} is a pull but pulls are not allowed")]
+    PullNotAllowed(usize),
+    /// All actions are valid, but the map is not solved.
+    #[error("map is not solved")]
+    Unsolved,
 }
 
 /// An error which can be returned when level do/undo/redo actions.
@@ -98,10 +156,94 @@ pub enum ActionError {
     /// Push in the specified direction is blocked.
     #[error("push in the specified direction is blocked")]
     PushBlocked,
+    /// Push into a static deadlock position is rejected.
+    #[error("push into a static deadlock position")]
+    PushIntoDeadlock,
+    /// There is no box at the specified position.
+    #[error("no box at the specified position")]
+    NoBox,
+    /// The target position is unreachable.
+    #[error("target position is unreachable")]
+    Unreachable,
     /// No actions available.
     #[error("no actions")]
     NoActions,
     /// No undone actions available.
     #[error("no undone actions")]
     NoUndoneActions,
+    /// There is no box behind the player to pull.
+    #[error("no box to pull")]
+    NoBoxToPull,
+    /// There is no free floor to place the player on.
+    #[error("no free floor")]
+    NoFreeFloor,
+    /// Jumps are not allowed after the first pull.
+    #[error("jump is not allowed after the first pull")]
+    JumpNotAllowed,
+    /// The specified branch does not exist.
+    #[error("no such branch")]
+    NoBranch,
+    /// Pulls are not allowed by the movement rules.
+    #[error("pulls are not allowed")]
+    PullNotAllowed,
+    /// Pushes are not allowed by the movement rules.
+    #[error("pushes are not allowed")]
+    PushNotAllowed,
+    /// There is no player with the specified index.
+    #[error("no player with index {0}")]
+    InvalidPlayer(usize),
+}
+
+/// An error which can be returned when editing a map.
+#[derive(Error, Clone, Eq, PartialEq, Debug)]
+pub enum EditError {
+    /// The position is out of bounds.
+    #[error("position is out of bounds")]
+    OutOfBounds,
+    /// The position is occupied by a wall, a box or the player.
+    #[error("position is occupied")]
+    Occupied,
+    /// There is no box at the specified position.
+    #[error("no box at the specified position")]
+    NoBox,
+    /// There is no goal at the specified position.
+    #[error("no goal at the specified position")]
+    NoGoal,
+}
+
+/// A problem found when validating a map.
+#[derive(Error, Clone, Eq, PartialEq, Debug)]
+pub enum ValidationError {
+    /// There is no player.
+    #[error("no player")]
+    NoPlayer,
+    /// The number of boxes and goals do not match.
+    #[error("mismatch between number of boxes and goals")]
+    BoxGoalMismatch,
+    /// There are no boxes or goals.
+    #[error("no box or goal")]
+    NoBoxOrGoal,
+    /// The floor at the specified position is on the edge of the map, so the
+    /// border is not enclosed by walls.
+    #[error("open border at ({}, {})", .0.x, .0.y)]
+    OpenBorder(Vector2<i32>),
+    /// The box at the specified position is unreachable by the player.
+    #[error("box at ({}, {}) is unreachable", .0.x, .0.y)]
+    UnreachableBox(Vector2<i32>),
+    /// The goal at the specified position is unreachable by the player.
+    #[error("goal at ({}, {}) is unreachable", .0.x, .0.y)]
+    UnreachableGoal(Vector2<i32>),
+    /// The box at the specified position starts in a static deadlock.
+    #[error("box at ({}, {}) is in a static deadlock", .0.x, .0.y)]
+    StaticDeadlock(Vector2<i32>),
+    /// The box at the specified position is not on a goal and can never be
+    /// moved.
+    #[error("box at ({}, {}) can never move", .0.x, .0.y)]
+    ImmovableBox(Vector2<i32>),
+    /// No box can be pushed to the goal at the specified position.
+    #[error("no box can be pushed to goal at ({}, {})", .0.x, .0.y)]
+    GoalUnreachableByBoxes(Vector2<i32>),
+    /// The floor at the specified position is useless.
+    #[error("useless floor at ({}, {})", .0.x, .0.y)]
+    UselessFloor(Vector2<i32>),
 }
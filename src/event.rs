@@ -0,0 +1,34 @@
+//! Events emitted when the state of a level changes.
+
+use nalgebra::Vector2;
+
+use crate::action::Action;
+
+/// An event emitted when the state of a level changes.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum Event {
+    /// The player moved from one position to another.
+    PlayerMoved {
+        /// The previous position of the player.
+        from: Vector2<i32>,
+        /// The new position of the player.
+        to: Vector2<i32>,
+    },
+    /// A box moved from one position to another.
+    BoxMoved {
+        /// The previous position of the box.
+        from: Vector2<i32>,
+        /// The new position of the box.
+        to: Vector2<i32>,
+    },
+    /// A box landed on the goal at the specified position.
+    BoxLandedOnGoal(Vector2<i32>),
+    /// A box left the goal at the specified position.
+    BoxLeftGoal(Vector2<i32>),
+    /// The level is solved.
+    Solved,
+    /// The action was undone.
+    Undone(Action),
+    /// The action was redone.
+    Redone(Action),
+}
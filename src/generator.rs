@@ -0,0 +1,257 @@
+//! A random level generator.
+
+use nalgebra::Vector2;
+
+use crate::{
+    direction::Direction,
+    error::GeneratorError,
+    map::Map,
+    path_finding::reachable_area,
+    reverse_level::ReverseLevel,
+    solver::{Solver, Strategy},
+    tiles::Tiles,
+};
+
+/// The templates used to build rooms, where `#` represents a wall.
+const TEMPLATES: [[&str; 3]; 10] = [
+    ["   ", "   ", "   "],
+    ["#  ", "   ", "   "],
+    ["## ", "   ", "   "],
+    ["###", "   ", "   "],
+    ["#  ", "#  ", "   "],
+    ["## ", "#  ", "   "],
+    ["   ", " # ", "   "],
+    ["#  ", "   ", "  #"],
+    ["# #", "   ", "   "],
+    ["## ", "## ", "   "],
+];
+
+/// The maximum number of candidates generated by [`Generator::generate`].
+const MAX_ATTEMPTS: usize = 100;
+
+/// The maximum number of nodes expanded by the solver for each candidate.
+///
+/// Candidates which can not be solved within the limit are discarded, so that
+/// large dimensions or box counts do not stall the generator.
+const NODE_LIMIT: usize = 20_000;
+
+/// The options of the generator.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct GeneratorOptions {
+    /// The dimensions of the room, including the surrounding walls.
+    pub dimensions: Vector2<i32>,
+    /// The number of boxes.
+    pub box_count: usize,
+    /// The seed of the random number generator.
+    pub seed: u64,
+    /// The minimum number of pushes of the push optimal solution. Candidates
+    /// solved with fewer pushes are discarded.
+    ///
+    /// This is a simple proxy for difficulty, see [`difficulty::estimate`] for
+    /// a more thorough estimate.
+    ///
+    /// [`difficulty::estimate`]: crate::difficulty::estimate
+    pub min_pushes: usize,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            dimensions: Vector2::new(9, 9),
+            box_count: 3,
+            seed: 0,
+            min_pushes: 0,
+        }
+    }
+}
+
+/// A random level generator.
+///
+/// The generator builds rooms from templates, places goals, and creates the
+/// start position by pulling the boxes away from the goals, which guarantees
+/// that the generated maps are solvable. The candidates are then filtered by
+/// the solver and normalized.
+///
+/// The generator is deterministic, the same options always generate the same
+/// maps.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    options: GeneratorOptions,
+    rng: Rng,
+}
+
+impl Generator {
+    /// Creates a new `Generator`.
+    ///
+    /// Returns an error if the dimensions are smaller than 3x3 or the box
+    /// count is zero.
+    pub fn new(options: GeneratorOptions) -> Result<Self, GeneratorError> {
+        if options.dimensions.x < 3 || options.dimensions.y < 3 {
+            return Err(GeneratorError::DimensionsTooSmall);
+        }
+        if options.box_count == 0 {
+            return Err(GeneratorError::NoBoxes);
+        }
+        Ok(Self {
+            options,
+            rng: Rng::new(options.seed),
+        })
+    }
+
+    /// Returns the options of the generator.
+    pub fn options(&self) -> &GeneratorOptions {
+        &self.options
+    }
+
+    /// Generates a normalized map.
+    ///
+    /// Returns `None` if no map meeting the options is found after a limited
+    /// number of attempts.
+    pub fn generate(&mut self) -> Option<Map> {
+        (0..MAX_ATTEMPTS).find_map(|_| self.generate_candidate())
+    }
+
+    /// Generates a candidate map, or `None` if it does not meet the options.
+    fn generate_candidate(&mut self) -> Option<Map> {
+        let mut map = self.generate_room()?;
+        let mut floors = floors(&map);
+        for _ in 0..=self.options.box_count {
+            let index = self.rng.below(floors.len());
+            let position = floors.swap_remove(index);
+            if map.goal_positions().len() < self.options.box_count {
+                map.add_goal(position).unwrap();
+                map.add_box(position).unwrap();
+            } else {
+                map.place_player(position).unwrap();
+            }
+        }
+
+        let mut map = self.pull_boxes(map)?;
+        if map.is_solved() {
+            return None;
+        }
+        map.normalize();
+        if map.box_positions().len() != self.options.box_count {
+            return None;
+        }
+
+        let mut solver = Solver::new(map.clone(), Strategy::OptimalPush);
+        solver.set_node_limit(Some(NODE_LIMIT));
+        let solution = solver.a_star_search().ok()?;
+        (solution.pushes() >= self.options.min_pushes).then_some(map)
+    }
+
+    /// Generates an empty room from templates.
+    ///
+    /// Returns `None` if the room is too small for the boxes.
+    fn generate_room(&mut self) -> Option<Map> {
+        let dimensions = self.options.dimensions;
+        let mut walls = vec![false; (dimensions.x * dimensions.y) as usize];
+        for y in (1..dimensions.y - 1).step_by(3) {
+            for x in (1..dimensions.x - 1).step_by(3) {
+                let template = TEMPLATES[self.rng.below(TEMPLATES.len())];
+                let (rotations, flip) = (self.rng.below(4), self.rng.below(2) == 1);
+                for (dy, row) in template.iter().enumerate() {
+                    for (dx, char) in row.chars().enumerate() {
+                        let mut offset = Vector2::new(dx as i32, dy as i32);
+                        for _ in 0..rotations {
+                            offset = Vector2::new(2 - offset.y, offset.x);
+                        }
+                        if flip {
+                            offset.x = 2 - offset.x;
+                        }
+                        let position = Vector2::new(x, y) + offset;
+                        if position.x < dimensions.x - 1 && position.y < dimensions.y - 1 {
+                            walls[(position.y * dimensions.x + position.x) as usize] = char == '#';
+                        }
+                    }
+                }
+            }
+        }
+
+        // Keep the area connected to a random floor
+        let is_floor = |position: Vector2<i32>| {
+            0 < position.x
+                && position.x < dimensions.x - 1
+                && 0 < position.y
+                && position.y < dimensions.y - 1
+                && !walls[(position.y * dimensions.x + position.x) as usize]
+        };
+        let floors: Vec<_> = (0..dimensions.y)
+            .flat_map(|y| (0..dimensions.x).map(move |x| Vector2::new(x, y)))
+            .filter(|&position| is_floor(position))
+            .collect();
+        if floors.is_empty() {
+            return None;
+        }
+        let area = reachable_area(floors[self.rng.below(floors.len())], is_floor);
+        if area.len() < self.options.box_count * 3 + 1 {
+            return None;
+        }
+
+        let mut map = Map::with_dimensions(dimensions);
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                let position = Vector2::new(x, y);
+                if !area.contains(&position) {
+                    map.set_wall(position).unwrap();
+                }
+            }
+        }
+        Some(map)
+    }
+
+    /// Pulls the boxes away from the goals randomly and returns the resulting
+    /// map.
+    fn pull_boxes(&mut self, map: Map) -> Option<Map> {
+        let steps = self.options.box_count * (map.dimensions().x * map.dimensions().y) as usize;
+        let mut level = ReverseLevel::from_map(map).ok()?;
+        for _ in 0..steps {
+            let direction = [
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+            ][self.rng.below(4)];
+            if self.rng.below(3) != 0 && level.do_pull(direction).is_ok() {
+                continue;
+            }
+            let _ = level.do_move(direction);
+        }
+        Some(level.map().clone())
+    }
+}
+
+/// Returns the positions of the floors in row-major order.
+fn floors(map: &Map) -> Vec<Vector2<i32>> {
+    (0..map.dimensions().y)
+        .flat_map(|y| (0..map.dimensions().x).map(move |x| Vector2::new(x, y)))
+        .filter(|&position| map[position] == Tiles::empty())
+        .collect()
+}
+
+/// A deterministic pseudorandom number generator based on SplitMix64.
+#[derive(Clone, Debug)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next random number.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in the range `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
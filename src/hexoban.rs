@@ -0,0 +1,251 @@
+//! Hexoban, the Sokoban variant played on a hexagonal grid.
+//!
+//! The cells of a hexagonal map are stored in doubled-width coordinates, which
+//! match the usual text layout of Hexoban levels, where each cell is two
+//! characters wide and every other row is offset by one character:
+//!
+//! ```text
+//!  # # # #
+//! # - $ . #
+//!  # @ # #
+//!   # #
+//! ```
+//!
+//! Only positions where `x + y` has the same parity as the map are cells.
+
+use std::{collections::HashSet, fmt, ops::Neg, str::FromStr};
+
+use nalgebra::Vector2;
+
+use crate::{
+    deadlock::calculate_static_deadlocks_in,
+    error::{ActionError, ParseMapError},
+    map::Map,
+    path_finding::reachable_area_in,
+    solver::GridSolver,
+    tiles::Tiles,
+    topology::{Grid, Hexagonal, Topology},
+};
+
+/// A direction on a hexagonal grid.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum HexDirection {
+    /// Rightward direction (positive X-axis).
+    East,
+    /// Leftward direction (negative X-axis).
+    West,
+    /// Up and to the right.
+    NorthEast,
+    /// Up and to the left.
+    NorthWest,
+    /// Down and to the right.
+    SouthEast,
+    /// Down and to the left.
+    SouthWest,
+}
+
+impl HexDirection {
+    /// Returns an iterator over all directions.
+    pub fn iter() -> std::array::IntoIter<HexDirection, 6> {
+        [
+            Self::East,
+            Self::West,
+            Self::NorthEast,
+            Self::NorthWest,
+            Self::SouthEast,
+            Self::SouthWest,
+        ]
+        .into_iter()
+    }
+}
+
+impl Neg for HexDirection {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Self::East => Self::West,
+            Self::West => Self::East,
+            Self::NorthEast => Self::SouthWest,
+            Self::NorthWest => Self::SouthEast,
+            Self::SouthEast => Self::NorthWest,
+            Self::SouthWest => Self::NorthEast,
+        }
+    }
+}
+
+impl From<HexDirection> for Vector2<i32> {
+    fn from(direction: HexDirection) -> Self {
+        use HexDirection as E;
+        match direction {
+            E::East => Vector2::new(2, 0),
+            E::West => Vector2::new(-2, 0),
+            E::NorthEast => Vector2::new(1, -1),
+            E::NorthWest => Vector2::new(-1, -1),
+            E::SouthEast => Vector2::new(1, 1),
+            E::SouthWest => Vector2::new(-1, 1),
+        }
+    }
+}
+
+impl TryFrom<Vector2<i32>> for HexDirection {
+    type Error = ();
+
+    fn try_from(vector: Vector2<i32>) -> Result<Self, Self::Error> {
+        Self::iter()
+            .find(|&direction| Vector2::from(direction) == vector)
+            .ok_or(())
+    }
+}
+
+/// A Hexoban map.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct HexMap {
+    map: Map,
+    parity: i32,
+}
+
+impl HexMap {
+    /// Checks if the position is a cell within the map bounds.
+    pub fn in_bounds(&self, position: Vector2<i32>) -> bool {
+        self.map.in_bounds(position) && (position.x + position.y).rem_euclid(2) == self.parity
+    }
+
+    /// Returns the tiles at the position, or `None` if it is not a cell within
+    /// the map bounds.
+    pub fn get(&self, position: Vector2<i32>) -> Option<&Tiles> {
+        self.in_bounds(position).then(|| &self.map[position])
+    }
+
+    /// Marks the cells reachable by the player, ignoring boxes, as floors.
+    fn update_floors(&mut self) {
+        let dimensions = self.map.dimensions();
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                self.map[Vector2::new(x, y)].remove(Tiles::Floor);
+            }
+        }
+        let floors = reachable_area_in::<Hexagonal>(self.map.player_position(), |position| {
+            self.get(position)
+                .is_some_and(|tiles| !tiles.intersects(Tiles::Wall))
+        });
+        for position in floors {
+            self.map[position].insert(Tiles::Floor);
+        }
+    }
+}
+
+impl Grid for HexMap {
+    type Topology = Hexagonal;
+
+    /// Returns the dimensions of the map in doubled-width coordinates.
+    fn dimensions(&self) -> Vector2<i32> {
+        self.map.dimensions()
+    }
+
+    fn player_position(&self) -> Vector2<i32> {
+        self.map.player_position()
+    }
+
+    fn box_positions(&self) -> &HashSet<Vector2<i32>> {
+        self.map.box_positions()
+    }
+
+    fn goal_positions(&self) -> &HashSet<Vector2<i32>> {
+        self.map.goal_positions()
+    }
+
+    fn is_floor(&self, position: Vector2<i32>) -> bool {
+        self.get(position)
+            .is_some_and(|tiles| tiles.intersects(Tiles::Floor))
+    }
+
+    fn do_move(&mut self, direction: HexDirection) -> Result<bool, ActionError> {
+        let new_player_position = Hexagonal::neighbor(self.player_position(), direction);
+        if self
+            .get(new_player_position)
+            .is_none_or(|tiles| tiles.intersects(Tiles::Wall))
+        {
+            return Err(ActionError::MoveBlocked);
+        }
+        let pushed = self.map[new_player_position].intersects(Tiles::Box);
+        if pushed {
+            let new_box_position = Hexagonal::neighbor(new_player_position, direction);
+            if self
+                .get(new_box_position)
+                .is_none_or(|tiles| tiles.intersects(Tiles::Wall | Tiles::Box))
+            {
+                return Err(ActionError::PushBlocked);
+            }
+            self.map
+                .set_box_position(new_player_position, new_box_position);
+        }
+        self.map.set_player_position(new_player_position);
+        Ok(pushed)
+    }
+}
+
+impl FromStr for HexMap {
+    type Err = ParseMapError;
+
+    /// Creates a new `HexMap` from a Hexoban format string.
+    ///
+    /// Empty cells may be written as `' '`, `'-'` or `'_'`.
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let map = Map::from_str(str)?;
+        // The parity of the cells is defined by the first non-empty tile
+        let dimensions = map.dimensions();
+        let mut parity = None;
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                let position = Vector2::new(x, y);
+                // Floors are recomputed below, since the flood fill of `Map`
+                // leaks through the gaps between hexagonal walls
+                if map[position].difference(Tiles::Floor).is_empty() {
+                    continue;
+                }
+                let parity = *parity.get_or_insert((x + y) % 2);
+                if (x + y) % 2 != parity {
+                    return Err(ParseMapError::MisalignedTile(position));
+                }
+            }
+        }
+        let mut instance = Self {
+            map,
+            parity: parity.unwrap_or(0),
+        };
+        instance.update_floors();
+        Ok(instance)
+    }
+}
+
+impl fmt::Display for HexMap {
+    /// Writes the map in Hexoban format, where floors are written as `'-'` to
+    /// distinguish them from the positions between the cells.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dimensions = self.map.dimensions();
+        for y in 0..dimensions.y {
+            let mut line = String::new();
+            for x in 0..dimensions.x {
+                let position = Vector2::new(x, y);
+                match self.get(position) {
+                    Some(&tiles) if tiles == Tiles::Floor => line.push('-'),
+                    Some(&tiles) if tiles != Tiles::empty() => line += &tiles.to_string(),
+                    _ => line.push(' '),
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Calculates static deadlock positions independent of the player's position.
+///
+/// See [`calculate_static_deadlocks_in`] for details.
+pub fn calculate_static_deadlocks(map: &HexMap) -> HashSet<Vector2<i32>> {
+    calculate_static_deadlocks_in(map)
+}
+
+/// A push optimal solver for Hexoban.
+pub type HexSolver = GridSolver<HexMap>;
@@ -1,10 +1,11 @@
 //! A level.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt,
     io::BufRead,
     str::FromStr,
+    time::SystemTime,
 };
 
 use itertools::Itertools;
@@ -13,22 +14,58 @@ use nalgebra::Vector2;
 use crate::{
     action::Action,
     actions::Actions,
+    deadlock::calculate_static_deadlocks_with_rules,
     direction::Direction,
     error::{ActionError, ParseLevelError, ParseMapError},
+    event::Event,
     map::Map,
-    path_finding::reachable_area,
+    path_finding::{box_move_actions, reachable_area, Optimization},
+    rules::MovementRules,
     tiles::Tiles,
+    undo_tree::UndoTree,
 };
 
+/// Options which control how actions are performed on a level.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub struct PlayOptions {
+    /// Interprets a move in the opposite direction of the last move as undoing
+    /// the last move.
+    pub undo_on_reversal: bool,
+    /// Rejects pushes and pulls of boxes into static deadlock positions.
+    pub reject_static_deadlocks: bool,
+    /// Records the time at which each action is performed.
+    pub record_timestamps: bool,
+}
+
 /// A level.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Level {
     map: Map,
     metadata: HashMap<String, String>,
     actions: Actions,
-    undone_actions: Actions,
+    timestamps: Vec<Option<SystemTime>>,
+    undo_tree: UndoTree,
+    play_options: PlayOptions,
+    movement_rules: MovementRules,
+    static_deadlocks: StaticDeadlocks,
+}
+
+/// The static deadlock positions of each box ID, calculated on demand.
+///
+/// Walls and goals do not change during play, so the positions are only
+/// recalculated when the map or the movement rules are replaced. The cache is
+/// ignored when comparing levels.
+#[derive(Clone, Default, Debug)]
+struct StaticDeadlocks(HashMap<u32, HashSet<Vector2<i32>>>);
+
+impl PartialEq for StaticDeadlocks {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
 
+impl Eq for StaticDeadlocks {}
+
 impl Level {
     /// Creates a new `Level` from map.
     pub fn from_map(map: Map) -> Self {
@@ -36,7 +73,11 @@ impl Level {
             map,
             metadata: HashMap::new(),
             actions: Actions::default(),
-            undone_actions: Actions::default(),
+            timestamps: Vec::new(),
+            undo_tree: UndoTree::default(),
+            play_options: PlayOptions::default(),
+            movement_rules: MovementRules::default(),
+            static_deadlocks: StaticDeadlocks::default(),
         }
     }
 
@@ -47,6 +88,7 @@ impl Level {
 
     /// Returns a mutable reference to the map of the level.
     pub fn map_mut(&mut self) -> &mut Map {
+        self.static_deadlocks.0.clear();
         &mut self.map
     }
 
@@ -55,11 +97,48 @@ impl Level {
         &self.metadata
     }
 
+    /// Returns a mutable reference to the metadata of the level.
+    ///
+    /// The keys are lowercase, and the values must not contain line breaks
+    /// except for the `comments` key.
+    pub fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.metadata
+    }
+
     /// Returns a reference to the actions of the level.
     pub fn actions(&self) -> &Actions {
         &self.actions
     }
 
+    /// Returns the times at which the actions were performed.
+    ///
+    /// The times are only recorded if [`PlayOptions::record_timestamps`] is
+    /// enabled, otherwise the corresponding element is `None`.
+    pub fn timestamps(&self) -> &[Option<SystemTime>] {
+        &self.timestamps
+    }
+
+    /// Returns a reference to the play options of the level.
+    pub fn play_options(&self) -> &PlayOptions {
+        &self.play_options
+    }
+
+    /// Sets the play options of the level.
+    pub fn set_play_options(&mut self, play_options: PlayOptions) {
+        self.play_options = play_options;
+    }
+
+    /// Returns the movement rules of the level.
+    pub fn movement_rules(&self) -> MovementRules {
+        self.movement_rules
+    }
+
+    /// Sets the movement rules of the level.
+    pub fn set_movement_rules(&mut self, movement_rules: MovementRules) {
+        self.movement_rules = movement_rules;
+        self.static_deadlocks.0.clear();
+    }
+
     /// Performs a sequence of actions on the level.
     pub fn do_actions<I: IntoIterator<Item = Direction>>(
         &mut self,
@@ -72,64 +151,203 @@ impl Level {
     }
 
     /// Moves the player in the specified direction.
-    pub fn do_action(&mut self, direction: Direction) -> Result<(), ActionError> {
-        if self.actions.last() == Some(&Action::Move(-direction)) {
-            self.undo_action().unwrap();
-            return Ok(());
+    ///
+    /// Returns the events caused by the action. The behavior can be configured
+    /// with [`PlayOptions`].
+    pub fn do_action(&mut self, direction: Direction) -> Result<Vec<Event>, ActionError> {
+        if self.play_options.undo_on_reversal
+            && self.actions.last() == Some(&Action::Move(-direction))
+        {
+            return Ok(self.undo_action().unwrap());
         }
 
-        let new_player_position = self.map.player_position() + &direction.into();
-        if self.map[new_player_position].intersects(Tiles::Wall) {
-            return Err(ActionError::MoveBlocked);
+        if self.play_options.reject_static_deadlocks {
+            let box_position = self.map.player_position() + &direction.into();
+            let new_box_position = box_position + &direction.into();
+            if self.map.box_positions().contains(&box_position)
+                && self.map.can_move(new_box_position)
+                && self.movement_rules.can_push()
+                && self
+                    .static_deadlocks(box_position)
+                    .contains(&new_box_position)
+            {
+                return Err(ActionError::PushIntoDeadlock);
+            }
         }
-        if self.map[new_player_position].intersects(Tiles::Box) {
-            let new_box_position = new_player_position + &direction.into();
-            if self.map[new_box_position].intersects(Tiles::Wall | Tiles::Box) {
-                return Err(ActionError::PushBlocked);
+
+        let mut events = Vec::new();
+        let action = self.perform_action(direction, &mut events)?;
+        self.record_action(action);
+        self.undo_tree.push(action);
+        Ok(events)
+    }
+
+    /// Moves the player in the specified direction and pulls the box behind
+    /// the player.
+    ///
+    /// Returns the events caused by the action. Pulls must be allowed by the
+    /// movement rules, see [`set_movement_rules`].
+    ///
+    /// [`set_movement_rules`]: Level::set_movement_rules
+    pub fn do_pull(&mut self, direction: Direction) -> Result<Vec<Event>, ActionError> {
+        if !self.movement_rules.can_pull() {
+            return Err(ActionError::PullNotAllowed);
+        }
+
+        if self.play_options.reject_static_deadlocks {
+            let player_position = self.map.player_position();
+            let box_position = player_position - &direction.into();
+            if self.map.box_positions().contains(&box_position)
+                && self.map.can_move(player_position + &direction.into())
+                && self
+                    .static_deadlocks(box_position)
+                    .contains(&player_position)
+            {
+                return Err(ActionError::PushIntoDeadlock);
             }
-            self.map
-                .set_box_position(new_player_position, new_box_position);
-            self.actions.push(Action::Push(direction));
-        } else {
-            self.actions.push(Action::Move(direction));
         }
-        self.map.set_player_position(new_player_position);
-        self.undone_actions.clear();
-        Ok(())
+
+        let mut events = Vec::new();
+        let action = self.perform_pull(direction, &mut events)?;
+        self.record_action(action);
+        self.undo_tree.push(action);
+        Ok(events)
     }
 
     /// Undoes the last action.
-    pub fn undo_action(&mut self) -> Result<(), ActionError> {
-        if let Some(last_action) = self.actions.pop() {
-            if last_action.is_push() {
-                let box_position = self.map.player_position() + &last_action.direction().into();
-                let prev_box_position = self.map.player_position();
-                self.map.set_box_position(box_position, prev_box_position);
+    ///
+    /// Returns the events caused by undoing the action. Actions performed
+    /// together, such as by [`push_box_to`], are undone together.
+    ///
+    /// [`push_box_to`]: Level::push_box_to
+    pub fn undo_action(&mut self) -> Result<Vec<Event>, ActionError> {
+        if let Some(last_actions) = self.undo_tree.undo() {
+            let mut events = Vec::new();
+            for last_action in last_actions.iter().rev().copied() {
+                self.actions.pop();
+                self.timestamps.pop();
+                events.push(Event::Undone(last_action));
+                let player_position = self.map.player_position();
+                let prev_player_position = player_position - &last_action.direction().into();
+                if last_action.is_pull() {
+                    let box_position = prev_player_position - &last_action.direction().into();
+                    self.move_box(prev_player_position, box_position, &mut events);
+                }
+                self.move_player(prev_player_position, &mut events);
+                if last_action.is_push() {
+                    let box_position = player_position + &last_action.direction().into();
+                    self.move_box(box_position, player_position, &mut events);
+                }
             }
-            let prev_player_position = self.map.player_position() - &last_action.direction().into();
-            self.map.set_player_position(prev_player_position);
-            self.undone_actions.push(last_action);
-            Ok(())
+            Ok(events)
         } else {
             Err(ActionError::NoActions)
         }
     }
 
-    /// Redoes the last action.
-    pub fn redo_action(&mut self) -> Result<(), ActionError> {
-        if let Some(last_undone_action) = self.undone_actions.pop() {
-            let undone_actions = std::mem::take(&mut self.undone_actions);
-            self.do_action(last_undone_action.direction()).unwrap();
-            self.undone_actions = undone_actions;
-            Ok(())
+    /// Redoes the last undone action.
+    ///
+    /// Returns the events caused by redoing the action. If there are multiple
+    /// branches, the most recent one is followed unless another branch is
+    /// selected with [`switch_branch`].
+    ///
+    /// [`switch_branch`]: Level::switch_branch
+    pub fn redo_action(&mut self) -> Result<Vec<Event>, ActionError> {
+        if let Some(undone_actions) = self.undo_tree.redo() {
+            let mut events = Vec::new();
+            for undone_action in undone_actions.iter().copied() {
+                events.push(Event::Redone(undone_action));
+                if undone_action.is_pull() {
+                    self.perform_pull(undone_action.direction(), &mut events)
+                } else {
+                    self.perform_action(undone_action.direction(), &mut events)
+                }
+                .unwrap();
+                self.record_action(undone_action);
+            }
+            Ok(events)
         } else {
             Err(ActionError::NoUndoneActions)
         }
     }
 
+    /// Pushes the box at the specified position to the target position.
+    ///
+    /// The route is planned by [`box_move_actions`] according to the specified
+    /// optimization, treating the other boxes as obstacles. The performed
+    /// actions are undone and redone as a single unit. Returns the events
+    /// caused by the actions.
+    ///
+    /// If [`PlayOptions::reject_static_deadlocks`] is enabled, routes that push
+    /// the box into a static deadlock position are rejected.
+    pub fn push_box_to(
+        &mut self,
+        box_position: Vector2<i32>,
+        target_position: Vector2<i32>,
+        optimization: Optimization,
+    ) -> Result<Vec<Event>, ActionError> {
+        if !self.map.box_positions().contains(&box_position) {
+            return Err(ActionError::NoBox);
+        }
+        if !self.movement_rules.can_push() {
+            return Err(ActionError::PushNotAllowed);
+        }
+        let actions = box_move_actions(&self.map, box_position, target_position, optimization)
+            .ok_or(ActionError::Unreachable)?;
+        if actions.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.play_options.reject_static_deadlocks {
+            let static_deadlocks = self.static_deadlocks(box_position);
+            let mut position = box_position;
+            for action in actions.iter().filter(|action| action.is_push()) {
+                position += &action.direction().into();
+                if static_deadlocks.contains(&position) {
+                    return Err(ActionError::PushIntoDeadlock);
+                }
+            }
+        }
+
+        // The actions performed before an error are still undone as a unit
+        let mut events = Vec::new();
+        let mut performed_actions = Actions::new();
+        let mut result = Ok(());
+        for action in actions.iter().copied() {
+            if let Err(error) = self.perform_action(action.direction(), &mut events) {
+                result = Err(error);
+                break;
+            }
+            self.record_action(action);
+            performed_actions.push(action);
+        }
+        if !performed_actions.is_empty() {
+            self.undo_tree.push_unit(performed_actions);
+        }
+        result.map(|_| events)
+    }
+
+    /// Returns a reference to the undo tree of the level.
+    pub fn undo_tree(&self) -> &UndoTree {
+        &self.undo_tree
+    }
+
+    /// Switches the branch followed by [`redo_action`].
+    ///
+    /// The branches at the current position are listed by
+    /// [`UndoTree::branches`].
+    ///
+    /// [`redo_action`]: Level::redo_action
+    pub fn switch_branch(&mut self, index: usize) -> Result<(), ActionError> {
+        if self.undo_tree.switch_branch(index) {
+            Ok(())
+        } else {
+            Err(ActionError::NoBranch)
+        }
+    }
+
     /// Returns true if the level is solved.
     pub fn is_solved(&self) -> bool {
-        self.map.box_positions() == self.map.goal_positions()
+        self.map.is_solved()
     }
 
     /// Returns the reachable area for the player.
@@ -167,6 +385,98 @@ impl Level {
         Self::from_str(&group)
     }
 
+    /// Moves the player in the specified direction without recording the
+    /// action, returns the performed action.
+    fn perform_action(
+        &mut self,
+        direction: Direction,
+        events: &mut Vec<Event>,
+    ) -> Result<Action, ActionError> {
+        let new_player_position = self.map.player_position() + &direction.into();
+        if self.map[new_player_position].intersects(Tiles::Wall) {
+            return Err(ActionError::MoveBlocked);
+        }
+        let action = if self.map[new_player_position].intersects(Tiles::Box) {
+            if !self.movement_rules.can_push() {
+                return Err(ActionError::MoveBlocked);
+            }
+            let new_box_position = new_player_position + &direction.into();
+            if self.map[new_box_position].intersects(Tiles::Wall | Tiles::Box) {
+                return Err(ActionError::PushBlocked);
+            }
+            self.move_box(new_player_position, new_box_position, events);
+            Action::Push(direction)
+        } else {
+            Action::Move(direction)
+        };
+        self.move_player(new_player_position, events);
+        if action.is_push() && self.is_solved() {
+            events.push(Event::Solved);
+        }
+        Ok(action)
+    }
+
+    /// Moves the player in the specified direction and pulls the box behind the
+    /// player without recording the action, returns the performed action.
+    fn perform_pull(
+        &mut self,
+        direction: Direction,
+        events: &mut Vec<Event>,
+    ) -> Result<Action, ActionError> {
+        let player_position = self.map.player_position();
+        let new_player_position = player_position + &direction.into();
+        if self.map[new_player_position].intersects(Tiles::Wall | Tiles::Box) {
+            return Err(ActionError::MoveBlocked);
+        }
+        let box_position = player_position - &direction.into();
+        if !self.map.box_positions().contains(&box_position) {
+            return Err(ActionError::NoBoxToPull);
+        }
+        self.move_player(new_player_position, events);
+        self.move_box(box_position, player_position, events);
+        if self.is_solved() {
+            events.push(Event::Solved);
+        }
+        Ok(Action::Pull(direction))
+    }
+
+    /// Returns the static deadlock positions of the box at the position under
+    /// the movement rules.
+    fn static_deadlocks(&mut self, box_position: Vector2<i32>) -> &HashSet<Vector2<i32>> {
+        let id = self.map.box_id(box_position);
+        self.static_deadlocks.0.entry(id).or_insert_with(|| {
+            calculate_static_deadlocks_with_rules(&self.map, self.movement_rules, id)
+        })
+    }
+
+    /// Moves the player to the specified position.
+    fn move_player(&mut self, position: Vector2<i32>, events: &mut Vec<Event>) {
+        events.push(Event::PlayerMoved {
+            from: self.map.player_position(),
+            to: position,
+        });
+        self.map.set_player_position(position);
+    }
+
+    /// Moves the box from one position to another.
+    fn move_box(&mut self, from: Vector2<i32>, to: Vector2<i32>, events: &mut Vec<Event>) {
+        self.map.set_box_position(from, to);
+        events.push(Event::BoxMoved { from, to });
+        if self.map[from].intersects(Tiles::Goal) {
+            events.push(Event::BoxLeftGoal(from));
+        }
+        if self.map[to].intersects(Tiles::Goal) {
+            events.push(Event::BoxLandedOnGoal(to));
+        }
+    }
+
+    /// Records the performed action.
+    fn record_action(&mut self, action: Action) {
+        self.actions.push(action);
+        self.timestamps
+            .push(self.play_options.record_timestamps.then(SystemTime::now));
+    }
+
     /// Lazily splits text from a reader into groups separated by empty lines
     /// (excluding empty lines within block comment), and filter out groups
     /// without map data.
@@ -223,9 +533,24 @@ impl Level {
 impl fmt::Display for Level {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.map)?;
-        self.metadata.iter();
-        for key in self.metadata.keys().sorted() {
-            let value = &self.metadata[key];
+        // The Sokoban+ sequences are regenerated, since the IDs follow the
+        // boxes and goals when the map is transformed
+        let mut metadata: BTreeMap<_, _> = self
+            .metadata
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "boxsequence" | "goalsequence"))
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        let sequences = [
+            ("boxsequence", self.map.box_sequence()),
+            ("goalsequence", self.map.goal_sequence()),
+        ];
+        for (key, sequence) in sequences {
+            if sequence.iter().any(|&id| id != 0) {
+                metadata.insert(key, sequence.iter().join(" "));
+            }
+        }
+        for (key, value) in metadata {
             if key == "comments" && value.lines().count() > 1 {
                 writeln!(f, "comment:")?;
                 for line in value.lines() {
@@ -234,6 +559,10 @@ impl fmt::Display for Level {
                 writeln!(f, "comment-end:")?;
                 continue;
             }
+            if key == "comments" {
+                writeln!(f, "comment: {}", value.trim_end())?;
+                continue;
+            }
             debug_assert!(
                 !value.contains('\n'),
                 "metadata value contains multiple line"
@@ -335,11 +664,18 @@ impl FromStr for Level {
             return Err(ParseLevelError::NoMap);
         }
 
+        let mut map = Map::from_str(&xsb[map_offset..map_offset + map_len])?;
+        apply_sokoban_plus_sequences(&mut map, &metadata)?;
+
         Ok(Self {
-            map: Map::from_str(&xsb[map_offset..map_offset + map_len])?,
+            map,
             metadata,
             actions: Actions::default(),
-            undone_actions: Actions::default(),
+            timestamps: Vec::new(),
+            undo_tree: UndoTree::default(),
+            play_options: PlayOptions::default(),
+            movement_rules: MovementRules::default(),
+            static_deadlocks: StaticDeadlocks::default(),
         })
     }
 }
@@ -420,6 +756,46 @@ trait GroupExt: BufRead {
 
 impl<T: BufRead> GroupExt for T {}
 
+/// Assigns the IDs of the Sokoban+ `BoxSequence` and `GoalSequence` metadata
+/// to the boxes and goals of the map.
+fn apply_sokoban_plus_sequences(
+    map: &mut Map,
+    metadata: &HashMap<String, String>,
+) -> Result<(), ParseLevelError> {
+    let parse_sequence = |key| -> Result<Option<Vec<u32>>, ParseLevelError> {
+        let Some(sequence) = metadata.get(key) else {
+            return Ok(None);
+        };
+        sequence
+            .split(|char: char| char.is_whitespace() || char == ',')
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse())
+            .collect::<Result<_, _>>()
+            .map(Some)
+            .map_err(|_| ParseLevelError::InvalidSequence(sequence.clone()))
+    };
+    if let Some(ids) = parse_sequence("boxsequence")? {
+        map.set_box_ids(&ids);
+    }
+    if let Some(ids) = parse_sequence("goalsequence")? {
+        map.set_goal_ids(&ids);
+    }
+
+    // Each ID must have as many boxes as goals
+    let box_ids = map
+        .box_positions()
+        .iter()
+        .map(|&position| map.box_id(position));
+    let goal_ids = map
+        .goal_positions()
+        .iter()
+        .map(|&position| map.goal_id(position));
+    if box_ids.sorted().ne(goal_ids.sorted()) {
+        return Err(ParseMapError::BoxGoalMismatch.into());
+    }
+    Ok(())
+}
+
 fn is_xsb_string(str: &str) -> bool {
     str.chars().all(is_xsb_symbol)
         || (str.chars().all(is_xsb_symbol_with_rle) && str.chars().any(is_xsb_symbol))
@@ -5,14 +5,26 @@
 pub mod action;
 pub mod actions;
 pub mod deadlock;
+pub mod difficulty;
 pub mod direction;
 pub mod error;
+pub mod event;
+pub mod generator;
+pub mod hexoban;
 pub mod level;
 pub mod map;
+pub mod multiban;
 pub mod path_finding;
+pub mod render;
+pub mod replay;
+pub mod reverse_level;
+pub mod rules;
 pub mod run_length;
 pub mod solver;
 pub mod tiles;
+pub mod topology;
+pub mod trioban;
+pub mod undo_tree;
 
 mod node;
 mod state;
@@ -11,10 +11,32 @@ use std::{
 use nalgebra::Vector2;
 
 use crate::{
-    actions::Actions, deadlock::*, direction::Direction, error::ParseMapError, level::Level,
-    path_finding::*, run_length::rle_decode, state::State, tiles::Tiles,
+    actions::{Actions, SecondaryValues},
+    deadlock::*,
+    direction::Direction,
+    error::{ActionError, EditError, ParseMapError, ValidationError, VerifyError},
+    path_finding::*,
+    replay::Replay,
+    rules::MovementRules,
+    run_length::rle_decode,
+    state::State,
+    tiles::Tiles,
+    topology::{Grid, Square},
 };
 
+/// Statistics of a verified solution.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct VerifiedSolution {
+    /// The number of moves.
+    pub moves: usize,
+    /// The number of pushes.
+    pub pushes: usize,
+    /// The number of pulls.
+    pub pulls: usize,
+    /// The secondary values.
+    pub secondary_values: SecondaryValues,
+}
+
 /// A grid-based map.
 ///
 /// Map is used to store the map data of the Sokoban level, which is saved in a
@@ -29,6 +51,10 @@ pub struct Map {
     player_position: Vector2<i32>,
     box_positions: HashSet<Vector2<i32>>,
     goal_positions: HashSet<Vector2<i32>>,
+
+    /// The non-zero IDs of the boxes and goals of Sokoban+ levels.
+    box_ids: HashMap<Vector2<i32>, u32>,
+    goal_ids: HashMap<Vector2<i32>, u32>,
 }
 
 impl Map {
@@ -47,6 +73,17 @@ impl Map {
         for action in &*actions {
             instance[current_player_position] = Tiles::Floor;
             current_player_position += &action.direction().into();
+            if action.is_pull() {
+                let new_box_position = current_player_position - &action.direction().into();
+                let box_position = new_box_position - &action.direction().into();
+                instance[box_position] = Tiles::Floor;
+                if !current_box_positions.contains(&box_position) {
+                    current_box_positions.insert(box_position);
+                    initial_box_positions.insert(box_position);
+                }
+                current_box_positions.remove(&box_position);
+                current_box_positions.insert(new_box_position);
+            }
             if action.is_push() {
                 instance[current_player_position + &action.direction().into()] = Tiles::Floor;
                 // The player pushed the box when moving, which means there is a box at the
@@ -86,14 +123,9 @@ impl Map {
         instance.goal_positions = goal_positions;
 
         // Verify the solution
-        let mut level = Level::from_map(instance.clone());
-        let directions = actions.iter().map(|action| action.direction());
-        level
-            .do_actions(directions)
+        instance
+            .verify_with_rules(&actions, MovementRules::PushAndPull)
             .map_err(|_| ParseMapError::InvalidActions)?;
-        if !level.map().is_solved() {
-            return Err(ParseMapError::InvalidActions);
-        }
 
         Ok(instance)
     }
@@ -109,6 +141,8 @@ impl Map {
             player_position: Vector2::zeros(),
             box_positions: HashSet::new(),
             goal_positions: HashSet::new(),
+            box_ids: HashMap::new(),
+            goal_ids: HashMap::new(),
         }
     }
 
@@ -140,14 +174,160 @@ impl Map {
     }
 
     /// Sets a box position from one to another.
+    ///
+    /// The ID of the box moves along with it.
     pub fn set_box_position(&mut self, from: Vector2<i32>, to: Vector2<i32>) {
+        let id = self.box_ids.remove(&from);
         self.remove_box_position(from);
         self.add_box_position(to);
+        if let Some(id) = id {
+            self.box_ids.insert(to, id);
+        }
+    }
+
+    /// Returns the ID of the box at the position.
+    ///
+    /// In Sokoban+ levels, each box must be pushed to a goal with the same ID.
+    /// Boxes without an ID, such as the boxes of standard levels, have the ID
+    /// 0.
+    pub fn box_id(&self, position: Vector2<i32>) -> u32 {
+        self.box_ids.get(&position).copied().unwrap_or(0)
+    }
+
+    /// Returns the ID of the goal at the position.
+    ///
+    /// See [`box_id`](Map::box_id) for details.
+    pub fn goal_id(&self, position: Vector2<i32>) -> u32 {
+        self.goal_ids.get(&position).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if any box or goal has a non-zero ID.
+    pub fn has_ids(&self) -> bool {
+        !self.box_ids.is_empty() || !self.goal_ids.is_empty()
+    }
+
+    /// Returns the IDs of the boxes in reading order, as the `BoxSequence` of
+    /// Sokoban+ levels.
+    pub fn box_sequence(&self) -> Vec<u32> {
+        Self::sequence(&self.box_positions, &self.box_ids)
+    }
+
+    /// Returns the IDs of the goals in reading order, as the `GoalSequence` of
+    /// Sokoban+ levels.
+    pub fn goal_sequence(&self) -> Vec<u32> {
+        Self::sequence(&self.goal_positions, &self.goal_ids)
+    }
+
+    /// Assigns IDs to the boxes in reading order, as the `BoxSequence` of
+    /// Sokoban+ levels.
+    ///
+    /// Boxes beyond the end of the sequence have the ID 0, and extra IDs are
+    /// ignored.
+    pub fn set_box_ids(&mut self, ids: &[u32]) {
+        self.box_ids = Self::assign_ids(&self.box_positions, ids);
+    }
+
+    /// Assigns IDs to the goals in reading order, as the `GoalSequence` of
+    /// Sokoban+ levels.
+    ///
+    /// See [`set_box_ids`](Map::set_box_ids) for details.
+    pub fn set_goal_ids(&mut self, ids: &[u32]) {
+        self.goal_ids = Self::assign_ids(&self.goal_positions, ids);
     }
 
     /// Returns `true` if the map is solved.
+    ///
+    /// Each box must be on a goal with the same ID.
     pub fn is_solved(&self) -> bool {
         self.box_positions == self.goal_positions
+            && self
+                .box_positions
+                .iter()
+                .all(|&position| self.box_id(position) == self.goal_id(position))
+    }
+
+    /// Verifies that the actions are a solution of the map.
+    ///
+    /// Unlike [`Level::do_actions`], the actions are replayed exactly as they
+    /// are. Reversal moves are not interpreted as undo actions, and each action
+    /// must be a push if and only if it pushes a box. Pulls are rejected, see
+    /// [`verify_with_rules`] to allow them.
+    ///
+    /// [`Level::do_actions`]: crate::Level::do_actions
+    /// [`verify_with_rules`]: Map::verify_with_rules
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::str::FromStr;
+    ///
+    /// use soukoban::{Actions, Map, VerifyError};
+    ///
+    /// let map = Map::from_str("#####|#@$.#|#####")?;
+    /// assert_eq!(map.verify(&Actions::from_str("R")?)?.pushes, 1);
+    /// assert_eq!(
+    ///     map.verify(&Actions::from_str("r")?),
+    ///     Err(VerifyError::PushDeclaredAsMove(0))
+    /// );
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn verify(&self, actions: &Actions) -> Result<VerifiedSolution, VerifyError> {
+        self.verify_with_rules(actions, MovementRules::PushOnly)
+    }
+
+    /// Verifies that the actions are a solution of the map under the movement
+    /// rules.
+    ///
+    /// See [`verify`] for details. [`verify`] only allows pushes.
+    ///
+    /// [`verify`]: Map::verify
+    pub fn verify_with_rules(
+        &self,
+        actions: &Actions,
+        movement_rules: MovementRules,
+    ) -> Result<VerifiedSolution, VerifyError> {
+        let mut replay = self.replay_with_rules(actions, movement_rules);
+        for snapshot in &mut replay {
+            snapshot?;
+        }
+        if !replay.is_solved() {
+            return Err(VerifyError::Unsolved);
+        }
+        Ok(VerifiedSolution {
+            moves: actions.moves(),
+            pushes: actions.pushes(),
+            pulls: actions.pulls(),
+            secondary_values: actions.secondary_values(),
+        })
+    }
+
+    /// Returns an iterator that replays the actions on the map.
+    ///
+    /// The iterator yields a snapshot after each action, without modifying the
+    /// map. The actions are replayed in the same way as [`verify`].
+    ///
+    /// [`verify`]: Map::verify
+    pub fn replay<'a>(&'a self, actions: &'a Actions) -> Replay<'a> {
+        self.replay_with_rules(actions, MovementRules::PushOnly)
+    }
+
+    /// Returns an iterator that replays the actions on the map under the
+    /// movement rules.
+    ///
+    /// The actions are replayed in the same way as [`verify_with_rules`].
+    ///
+    /// [`verify_with_rules`]: Map::verify_with_rules
+    pub fn replay_with_rules<'a>(
+        &'a self,
+        actions: &'a Actions,
+        movement_rules: MovementRules,
+    ) -> Replay<'a> {
+        Replay::new(self, actions, movement_rules)
     }
 
     /// Normalizes the map.
@@ -166,6 +346,69 @@ impl Map {
         self.normalize_transformation();
     }
 
+    /// Returns the canonical form of the map.
+    ///
+    /// The canonical form is the normalized map, see [`normalize`]. Maps that
+    /// only differ by rotation, reflection, the position of the player within
+    /// its reachable area or elements that are irrelevant to the solution have
+    /// the same canonical form.
+    ///
+    /// [`normalize`]: Map::normalize
+    pub fn canonical_form(&self) -> Map {
+        let mut canonical_form = self.clone();
+        canonical_form.normalize();
+        canonical_form
+    }
+
+    /// Returns the fingerprint of the map.
+    ///
+    /// The fingerprint is the hash of the canonical form of the map, see
+    /// [`canonical_form`]. It can be used to detect duplicate levels.
+    ///
+    /// The fingerprint is the smallest FNV-1a hash over the symmetries of the
+    /// canonical form, so it is stable across toolchains and can be stored.
+    ///
+    /// [`canonical_form`]: Map::canonical_form
+    pub fn fingerprint(&self) -> u64 {
+        let mut canonical_form = self.canonical_form();
+        let mut fingerprint = u64::MAX;
+        for i in 0..8 {
+            if i == 4 {
+                canonical_form.flip();
+            }
+            canonical_form.rotate();
+            canonical_form.normalize_player_position();
+            fingerprint = fingerprint.min(canonical_form.stable_hash());
+        }
+        fingerprint
+    }
+
+    /// Returns the FNV-1a hash of the dimensions, tiles and IDs of the map.
+    fn stable_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+            }
+        };
+        write(&self.dimensions.x.to_le_bytes());
+        write(&self.dimensions.y.to_le_bytes());
+        for tiles in &self.data {
+            write(&[tiles.bits()]);
+        }
+        for ids in [&self.box_ids, &self.goal_ids] {
+            let mut ids: Vec<_> = ids.iter().collect();
+            ids.sort_by_key(|(position, _)| (position.y, position.x));
+            write(&(ids.len() as u32).to_le_bytes());
+            for (position, id) in ids {
+                write(&position.x.to_le_bytes());
+                write(&position.y.to_le_bytes());
+                write(&id.to_le_bytes());
+            }
+        }
+        hash
+    }
+
     /// Shrinks the dimensions of the map by trims the empty area around the
     /// map.
     pub fn shrink_to_fit(&mut self) {
@@ -242,6 +485,7 @@ impl Map {
             .iter()
             .map(|position| position - offset)
             .collect();
+        self.transform_ids(|position| position - offset);
     }
 
     /// Returns tiles at the specified position or `None` if out of bounds.
@@ -252,6 +496,12 @@ impl Map {
 
     /// Returns a mutable reference to tiles at the specified position or `None`
     /// if out of bounds.
+    ///
+    /// Modifying the tiles directly does not update the positions of the
+    /// player, boxes and goals. Consider using the editing methods such as
+    /// [`add_box`] instead.
+    ///
+    /// [`add_box`]: Map::add_box
     pub fn get_mut(&mut self, position: Vector2<i32>) -> Option<&mut Tiles> {
         self.data
             .get_mut((position.y * self.dimensions.x + position.x) as usize)
@@ -319,6 +569,242 @@ impl Map {
         self.transform(flip_position, self.dimensions);
     }
 
+    /// Places a wall at the given position.
+    ///
+    /// The box and goal at the position are removed.
+    pub fn set_wall(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        self.check_bounds(position)?;
+        if self.has_player() && position == self.player_position {
+            return Err(EditError::Occupied);
+        }
+        if self.box_positions.contains(&position) {
+            self.remove_box_position(position);
+        }
+        if self.goal_positions.contains(&position) {
+            self.remove_goal_position(position);
+        }
+        self[position].insert(Tiles::Wall);
+        self.update_floors();
+        Ok(())
+    }
+
+    /// Removes the wall at the given position.
+    pub fn remove_wall(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        self.check_bounds(position)?;
+        self[position].remove(Tiles::Wall);
+        self.update_floors();
+        Ok(())
+    }
+
+    /// Adds a box at the given position.
+    pub fn add_box(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        self.check_bounds(position)?;
+        if self[position].intersects(Tiles::Wall | Tiles::Box | Tiles::Player) {
+            return Err(EditError::Occupied);
+        }
+        self.add_box_position(position);
+        Ok(())
+    }
+
+    /// Removes the box at the given position.
+    pub fn remove_box(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        if !self.box_positions.contains(&position) {
+            return Err(EditError::NoBox);
+        }
+        self.remove_box_position(position);
+        Ok(())
+    }
+
+    /// Adds a goal at the given position.
+    pub fn add_goal(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        self.check_bounds(position)?;
+        if self[position].intersects(Tiles::Wall | Tiles::Goal) {
+            return Err(EditError::Occupied);
+        }
+        self[position].insert(Tiles::Goal);
+        self.goal_positions.insert(position);
+        Ok(())
+    }
+
+    /// Removes the goal at the given position.
+    pub fn remove_goal(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        if !self.goal_positions.contains(&position) {
+            return Err(EditError::NoGoal);
+        }
+        self.remove_goal_position(position);
+        Ok(())
+    }
+
+    /// Places the player at the given position.
+    pub fn place_player(&mut self, position: Vector2<i32>) -> Result<(), EditError> {
+        self.check_bounds(position)?;
+        if self[position].intersects(Tiles::Wall | Tiles::Box) {
+            return Err(EditError::Occupied);
+        }
+        if self.has_player() {
+            self.index_mut(self.player_position).remove(Tiles::Player);
+        }
+        self[position].insert(Tiles::Player);
+        self.player_position = position;
+        self.update_floors();
+        Ok(())
+    }
+
+    /// Resizes the map to the provided dimensions, moving the original map to
+    /// the specified offset.
+    ///
+    /// Tiles outside the new dimensions are discarded. Returns an error if the
+    /// player would be outside the new dimensions.
+    pub fn resize(
+        &mut self,
+        new_dimensions: Vector2<i32>,
+        offset: Vector2<i32>,
+    ) -> Result<(), EditError> {
+        let in_new_bounds = |position: Vector2<i32>| {
+            let position = position + offset;
+            0 <= position.x
+                && position.x < new_dimensions.x
+                && 0 <= position.y
+                && position.y < new_dimensions.y
+        };
+        if self.has_player() && !in_new_bounds(self.player_position) {
+            return Err(EditError::OutOfBounds);
+        }
+
+        let mut resized_map = Map::with_dimensions(new_dimensions);
+        for y in 0..self.dimensions.y {
+            for x in 0..self.dimensions.x {
+                let position = Vector2::new(x, y);
+                if in_new_bounds(position) {
+                    resized_map[position + offset] = self[position];
+                }
+            }
+        }
+        self.data = resized_map.data;
+        self.dimensions = new_dimensions;
+
+        self.player_position += offset;
+        self.box_positions = self
+            .box_positions
+            .iter()
+            .filter(|&&position| in_new_bounds(position))
+            .map(|position| position + offset)
+            .collect();
+        self.goal_positions = self
+            .goal_positions
+            .iter()
+            .filter(|&&position| in_new_bounds(position))
+            .map(|position| position + offset)
+            .collect();
+        self.box_ids.retain(|&position, _| in_new_bounds(position));
+        self.goal_ids.retain(|&position, _| in_new_bounds(position));
+        self.transform_ids(|position| position + offset);
+        self.update_floors();
+        Ok(())
+    }
+
+    /// Clears the tiles which are not reachable by the player and encloses the
+    /// reachable area with walls.
+    pub fn fill_exterior(&mut self) {
+        if !self.has_player() {
+            return;
+        }
+        for y in 0..self.dimensions.y {
+            for x in 0..self.dimensions.x {
+                let position = Vector2::new(x, y);
+                if self[position].intersects(Tiles::Floor) {
+                    continue;
+                }
+                self[position] = Tiles::empty();
+                self.box_positions.remove(&position);
+                self.goal_positions.remove(&position);
+                self.box_ids.remove(&position);
+                self.goal_ids.remove(&position);
+            }
+        }
+        self.add_walls_around_floors();
+    }
+
+    /// Returns the problems found in the map.
+    ///
+    /// The deadlock and useless floor checks are only performed if the map is
+    /// enclosed by walls and all boxes and goals are reachable by the player.
+    /// Returns an empty list if no problems are found.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if !self.has_player() {
+            errors.push(ValidationError::NoPlayer);
+        }
+        if self.box_positions.len() != self.goal_positions.len() {
+            errors.push(ValidationError::BoxGoalMismatch);
+        }
+        if self.box_positions.is_empty() || self.goal_positions.is_empty() {
+            errors.push(ValidationError::NoBoxOrGoal);
+        }
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        let positions = || {
+            (0..self.dimensions.y)
+                .flat_map(|y| (0..self.dimensions.x).map(move |x| Vector2::new(x, y)))
+        };
+        let is_floor = |position: Vector2<i32>| self[position].intersects(Tiles::Floor);
+        errors.extend(
+            positions()
+                .filter(|&position| {
+                    is_floor(position)
+                        && (position.x == 0
+                            || position.y == 0
+                            || position.x == self.dimensions.x - 1
+                            || position.y == self.dimensions.y - 1)
+                })
+                .map(ValidationError::OpenBorder),
+        );
+        errors.extend(
+            positions()
+                .filter(|position| self.box_positions.contains(position))
+                .filter(|&position| !is_floor(position))
+                .map(ValidationError::UnreachableBox),
+        );
+        errors.extend(
+            positions()
+                .filter(|position| self.goal_positions.contains(position))
+                .filter(|&position| !is_floor(position))
+                .map(ValidationError::UnreachableGoal),
+        );
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        let static_deadlocks = calculate_static_deadlocks(self);
+        let useless_boxes = calculate_useless_boxes(self);
+        let push_distances = PushDistanceTable::new(self);
+        let useless_floors = calculate_useless_floors(self.clone());
+        for position in positions() {
+            if self[position].contains(Tiles::Box) && !self[position].intersects(Tiles::Goal) {
+                if static_deadlocks.contains(&position) {
+                    errors.push(ValidationError::StaticDeadlock(position));
+                }
+                if useless_boxes.contains(&position) {
+                    errors.push(ValidationError::ImmovableBox(position));
+                }
+            }
+            if self.goal_positions.contains(&position)
+                && !self
+                    .box_positions
+                    .iter()
+                    .any(|&box_position| push_distances.distance(box_position, position).is_some())
+            {
+                errors.push(ValidationError::GoalUnreachableByBoxes(position));
+            }
+            if useless_floors.contains(&position) {
+                errors.push(ValidationError::UselessFloor(position));
+            }
+        }
+        errors
+    }
+
     /// Adds a box at the given position.
     fn add_box_position(&mut self, position: Vector2<i32>) {
         debug_assert!(
@@ -337,6 +823,30 @@ impl Map {
         );
         self[position].remove(Tiles::Box);
         self.box_positions.remove(&position);
+        self.box_ids.remove(&position);
+    }
+
+    /// Returns `true` if the map has a player.
+    fn has_player(&self) -> bool {
+        self.in_bounds(self.player_position) && self[self.player_position].intersects(Tiles::Player)
+    }
+
+    /// Returns an error if the position is out of bounds.
+    fn check_bounds(&self, position: Vector2<i32>) -> Result<(), EditError> {
+        if !self.in_bounds(position) {
+            return Err(EditError::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    /// Recalculates the floors, which are the tiles reachable by the player.
+    fn update_floors(&mut self) {
+        self.data
+            .iter_mut()
+            .for_each(|tiles| tiles.remove(Tiles::Floor));
+        if self.has_player() {
+            self.flood_fill(self.player_position, Tiles::Floor, Tiles::Wall);
+        }
     }
 
     /// Removes a goal at the given position.
@@ -347,6 +857,7 @@ impl Map {
         );
         self[position].remove(Tiles::Goal);
         self.goal_positions.remove(&position);
+        self.goal_ids.remove(&position);
     }
 
     /// Sets unused floors to walls.
@@ -398,9 +909,12 @@ impl Map {
     }
 
     /// Normalizes the transformation of the map.
+    ///
+    /// Selects the transformation with the smallest hash. Ties are broken by
+    /// comparing the dimensions and the tiles, so the result does not depend on
+    /// the initial transformation of the map.
     fn normalize_transformation(&mut self) {
-        let mut transformed_maps = HashMap::with_capacity(8);
-        let mut min_hash = u64::MAX;
+        let mut transformed_maps = Vec::with_capacity(8);
         for i in 0..8 {
             if i == 4 {
                 self.flip();
@@ -410,12 +924,19 @@ impl Map {
 
             let mut hasher = DefaultHasher::new();
             self.hash(&mut hasher);
-            let hash = hasher.finish();
-
-            transformed_maps.insert(hash, self.clone());
-            min_hash = min_hash.min(hash);
+            transformed_maps.push((hasher.finish(), self.clone()));
         }
-        *self = transformed_maps.remove(&min_hash).unwrap();
+        *self = transformed_maps
+            .into_iter()
+            .min_by(|(a_hash, a), (b_hash, b)| {
+                a_hash
+                    .cmp(b_hash)
+                    .then_with(|| a.dimensions.x.cmp(&b.dimensions.x))
+                    .then_with(|| a.dimensions.y.cmp(&b.dimensions.y))
+                    .then_with(|| a.data.cmp(&b.data))
+            })
+            .unwrap()
+            .1;
     }
 
     /// Normalizes the position of the player on the map.
@@ -443,6 +964,41 @@ impl Map {
         self.player_position = operation(self.player_position);
         self.box_positions = self.box_positions.iter().copied().map(operation).collect();
         self.goal_positions = self.goal_positions.iter().copied().map(operation).collect();
+        self.transform_ids(operation);
+    }
+
+    /// Moves the IDs of the boxes and goals with the positions.
+    fn transform_ids(&mut self, operation: impl Fn(Vector2<i32>) -> Vector2<i32>) {
+        self.box_ids = self
+            .box_ids
+            .iter()
+            .map(|(&position, &id)| (operation(position), id))
+            .collect();
+        self.goal_ids = self
+            .goal_ids
+            .iter()
+            .map(|(&position, &id)| (operation(position), id))
+            .collect();
+    }
+
+    /// Assigns the IDs to the positions in reading order, omitting zeros.
+    fn assign_ids(positions: &HashSet<Vector2<i32>>, ids: &[u32]) -> HashMap<Vector2<i32>, u32> {
+        let mut positions: Vec<_> = positions.iter().copied().collect();
+        positions.sort_by_key(|position| (position.y, position.x));
+        positions
+            .into_iter()
+            .zip(ids.iter().copied())
+            .filter(|&(_, id)| id != 0)
+            .collect()
+    }
+
+    fn sequence(positions: &HashSet<Vector2<i32>>, ids: &HashMap<Vector2<i32>, u32>) -> Vec<u32> {
+        let mut positions: Vec<_> = positions.iter().collect();
+        positions.sort_by_key(|position| (position.y, position.x));
+        positions
+            .into_iter()
+            .map(|position| ids.get(position).copied().unwrap_or(0))
+            .collect()
     }
 
     fn add_walls_around_floors(&mut self) {
@@ -471,35 +1027,17 @@ impl Map {
         }
     }
 
-    /// Performs a flood fill algorithm starting from the specified position,
-    /// updating the tiles with the value provided within the area surrounded by
-    /// the provided border.
-    fn flood_fill(&mut self, position: Vector2<i32>, value: Tiles, border: Tiles) {
-        let mut deque = VecDeque::new();
-        deque.push_back(position);
-        while let Some(position) = deque.pop_front() {
-            if !self.in_bounds(position) || self[position].intersects(value | border) {
-                continue;
-            }
-            self[position].insert(value);
-            for direction in Direction::iter() {
-                let neighbor = position + &direction.into();
-                deque.push_back(neighbor);
-            }
-        }
-    }
-}
-
-impl FromStr for Map {
-    type Err = ParseMapError;
-
-    /// Creates a new `Map` from XSB format string.
+    /// Creates a new `Map` from XSB format string, and returns it along with
+    /// the positions of the players in reading order and the indentation
+    /// trimmed from the rows.
     ///
-    /// `Map` assumes that the map has a complete exterior wall and a solution.
-    /// Some invalid maps will return [`Err`] when created.
-    /// Returning [`Ok`] does not mean the map is fully valid, as it is
-    /// difficult or even impossible to verify that the map is fully valid.
-    fn from_str(xsb: &str) -> Result<Self, Self::Err> {
+    /// If `multiple_players` is `true`, the map may contain several players, as
+    /// in Multiban levels. The player of the map is the first one, and the
+    /// floors are the positions reachable by any player.
+    pub(crate) fn parse(
+        xsb: &str,
+        multiple_players: bool,
+    ) -> Result<(Self, Vec<Vector2<i32>>, i32), ParseMapError> {
         debug_assert!(!xsb.trim().is_empty(), "string is empty");
 
         // Calculate map dimensions and indentation
@@ -525,7 +1063,7 @@ impl FromStr for Map {
         let mut instance = Map::with_dimensions(dimensions);
 
         // Parse map data
-        let mut player_position = None;
+        let mut player_positions = Vec::new();
         for (y, line) in buf.lines().enumerate() {
             // Trim map indentation
             let line = &line[indent as usize..];
@@ -543,10 +1081,10 @@ impl FromStr for Map {
                         Tiles::Goal
                     }
                     '@' => {
-                        if player_position.is_some() {
+                        if !multiple_players && !player_positions.is_empty() {
                             return Err(ParseMapError::MoreThanOnePlayer);
                         }
-                        player_position = Some(position);
+                        player_positions.push(position);
                         Tiles::Player
                     }
                     '*' => {
@@ -555,10 +1093,10 @@ impl FromStr for Map {
                         Tiles::Box | Tiles::Goal
                     }
                     '+' => {
-                        if player_position.is_some() {
+                        if !multiple_players && !player_positions.is_empty() {
                             return Err(ParseMapError::MoreThanOnePlayer);
                         }
-                        player_position = Some(position);
+                        player_positions.push(position);
                         instance.goal_positions.insert(position);
                         Tiles::Player | Tiles::Goal
                     }
@@ -572,15 +1110,93 @@ impl FromStr for Map {
         if instance.box_positions.is_empty() {
             return Err(ParseMapError::NoBoxOrGoal);
         }
-        if let Some(player_position) = player_position {
-            instance.player_position = player_position;
-        } else {
+        let Some(&player_position) = player_positions.first() else {
             return Err(ParseMapError::NoPlayer);
+        };
+        instance.player_position = player_position;
+
+        for &player_position in &player_positions {
+            instance.flood_fill(player_position, Tiles::Floor, Tiles::Wall);
         }
 
-        instance.flood_fill(instance.player_position, Tiles::Floor, Tiles::Wall);
+        Ok((instance, player_positions, indent))
+    }
 
-        Ok(instance)
+    /// Performs a flood fill algorithm starting from the specified position,
+    /// updating the tiles with the value provided within the area surrounded by
+    /// the provided border.
+    fn flood_fill(&mut self, position: Vector2<i32>, value: Tiles, border: Tiles) {
+        let mut deque = VecDeque::new();
+        deque.push_back(position);
+        while let Some(position) = deque.pop_front() {
+            if !self.in_bounds(position) || self[position].intersects(value | border) {
+                continue;
+            }
+            self[position].insert(value);
+            for direction in Direction::iter() {
+                let neighbor = position + &direction.into();
+                deque.push_back(neighbor);
+            }
+        }
+    }
+}
+
+impl FromStr for Map {
+    type Err = ParseMapError;
+
+    /// Creates a new `Map` from XSB format string.
+    ///
+    /// `Map` assumes that the map has a complete exterior wall and a solution.
+    /// Some invalid maps will return [`Err`] when created.
+    /// Returning [`Ok`] does not mean the map is fully valid, as it is
+    /// difficult or even impossible to verify that the map is fully valid.
+    fn from_str(xsb: &str) -> Result<Self, Self::Err> {
+        Self::parse(xsb, false).map(|(map, ..)| map)
+    }
+}
+
+/// Box and goal IDs are ignored, since the topology-agnostic algorithms do not
+/// support Sokoban+.
+impl Grid for Map {
+    type Topology = Square;
+
+    fn dimensions(&self) -> Vector2<i32> {
+        self.dimensions
+    }
+
+    fn player_position(&self) -> Vector2<i32> {
+        self.player_position
+    }
+
+    fn box_positions(&self) -> &HashSet<Vector2<i32>> {
+        &self.box_positions
+    }
+
+    fn goal_positions(&self) -> &HashSet<Vector2<i32>> {
+        &self.goal_positions
+    }
+
+    fn is_floor(&self, position: Vector2<i32>) -> bool {
+        self.get(position)
+            .is_some_and(|tiles| tiles.intersects(Tiles::Floor))
+    }
+
+    fn do_move(&mut self, direction: Direction) -> Result<bool, ActionError> {
+        let new_player_position = self.player_position + &direction.into();
+        if !self.in_bounds(new_player_position) || self[new_player_position].intersects(Tiles::Wall)
+        {
+            return Err(ActionError::MoveBlocked);
+        }
+        let pushed = self[new_player_position].intersects(Tiles::Box);
+        if pushed {
+            let new_box_position = new_player_position + &direction.into();
+            if !self.can_move(new_box_position) {
+                return Err(ActionError::PushBlocked);
+            }
+            self.set_box_position(new_player_position, new_box_position);
+        }
+        self.set_player_position(new_player_position);
+        Ok(pushed)
     }
 }
 
@@ -605,6 +1221,13 @@ impl IndexMut<Vector2<i32>> for Map {
 impl Hash for Map {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.data.hash(state);
+        if self.has_ids() {
+            for ids in [&self.box_ids, &self.goal_ids] {
+                let mut ids: Vec<_> = ids.iter().collect();
+                ids.sort_by_key(|(position, _)| (position.y, position.x));
+                ids.hash(state);
+            }
+        }
     }
 }
 
@@ -625,6 +1248,7 @@ impl From<Map> for State {
         Self {
             player_position: map.player_position,
             box_positions: map.box_positions,
+            box_ids: map.box_ids,
         }
     }
 }
@@ -641,6 +1265,13 @@ fn calculate_dimensions_and_player_position(actions: &Actions) -> (Vector2<i32>,
             let box_position = player_position + &action.direction().into();
             min_position = min_position.zip_map(&box_position, std::cmp::min);
             max_position = max_position.zip_map(&box_position, std::cmp::max);
+        } else if action.is_pull() {
+            let box_position =
+                player_position - &action.direction().into() - &action.direction().into();
+            for position in [box_position, player_position] {
+                min_position = min_position.zip_map(&position, std::cmp::min);
+                max_position = max_position.zip_map(&position, std::cmp::max);
+            }
         } else {
             min_position = min_position.zip_map(&player_position, std::cmp::min);
             max_position = max_position.zip_map(&player_position, std::cmp::max);
@@ -0,0 +1,594 @@
+//! Multiban, the Sokoban variant with several players.
+//!
+//! Multiban levels use the XSB format, but may contain more than one player.
+//! The players are numbered in reading order and act one at a time. Each
+//! player moves and pushes boxes as in Sokoban, and the other players are
+//! obstacles to it.
+//!
+//! In the LURD notation, `[n]` switches to the `n`-th player, counting from 1.
+//! Actions before the first switch are performed by the first player:
+//!
+//! ```
+//! use soukoban::multiban::MultiActions;
+//!
+//! let actions: MultiActions = "rR[2]uu[1]L".parse().unwrap();
+//! assert_eq!(actions[2].player, 1);
+//! assert_eq!(actions.to_string(), "rR[2]uu[1]L");
+//! ```
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::{Deref, DerefMut, Index, IndexMut},
+    str::FromStr,
+};
+
+use nalgebra::Vector2;
+
+use crate::{
+    action::Action,
+    actions::Actions,
+    direction::Direction,
+    error::{ActionError, ParseActionsError, ParseMapError, SearchError},
+    event::Event,
+    map::Map,
+    path_finding::box_move_distances,
+    rules::MovementRules,
+    solver::{a_star_search, SearchProblem},
+    tiles::Tiles,
+    topology::Square,
+    undo_tree::UndoTree,
+};
+
+/// A Multiban map.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MultiMap {
+    map: Map,
+    player_positions: Vec<Vector2<i32>>,
+}
+
+impl MultiMap {
+    /// Returns the dimensions of the map.
+    pub fn dimensions(&self) -> Vector2<i32> {
+        self.map.dimensions()
+    }
+
+    /// Returns the positions of the players, in reading order.
+    pub fn player_positions(&self) -> &[Vector2<i32>] {
+        &self.player_positions
+    }
+
+    /// Returns the positions of the boxes.
+    pub fn box_positions(&self) -> &HashSet<Vector2<i32>> {
+        self.map.box_positions()
+    }
+
+    /// Returns the positions of the goals.
+    pub fn goal_positions(&self) -> &HashSet<Vector2<i32>> {
+        self.map.goal_positions()
+    }
+
+    /// Returns the tiles at the position, or `None` if it is out of bounds.
+    pub fn get(&self, position: Vector2<i32>) -> Option<&Tiles> {
+        self.map.get(position)
+    }
+
+    /// Checks if the position is within the map bounds.
+    pub fn in_bounds(&self, position: Vector2<i32>) -> bool {
+        self.map.in_bounds(position)
+    }
+
+    /// Checks if a player can move to the position, i.e. it is a floor without
+    /// a box or another player.
+    pub fn can_move(&self, position: Vector2<i32>) -> bool {
+        self.get(position).is_some_and(|tiles| {
+            tiles.intersects(Tiles::Floor) && !tiles.intersects(Tiles::Box | Tiles::Player)
+        })
+    }
+
+    /// Checks if the map is solved.
+    pub fn is_solved(&self) -> bool {
+        self.map.is_solved()
+    }
+
+    /// Moves the player with the index in the direction, pushing the box in
+    /// front of it if any.
+    fn do_move(&mut self, player: usize, direction: Direction) -> Result<Action, ActionError> {
+        let player_position = *self
+            .player_positions
+            .get(player)
+            .ok_or(ActionError::InvalidPlayer(player))?;
+        let new_player_position = player_position + &direction.into();
+        if self
+            .get(new_player_position)
+            .is_none_or(|tiles| tiles.intersects(Tiles::Wall | Tiles::Player))
+        {
+            return Err(ActionError::MoveBlocked);
+        }
+        let action = if self.map[new_player_position].intersects(Tiles::Box) {
+            let new_box_position = new_player_position + &direction.into();
+            if !self.can_move(new_box_position) {
+                return Err(ActionError::PushBlocked);
+            }
+            self.map
+                .set_box_position(new_player_position, new_box_position);
+            Action::Push(direction)
+        } else {
+            Action::Move(direction)
+        };
+        self.set_player_position(player, new_player_position);
+        Ok(action)
+    }
+
+    /// Moves the player with the index to the position.
+    ///
+    /// Only the player tiles are updated, since the player of the underlying
+    /// map is not used.
+    fn set_player_position(&mut self, player: usize, position: Vector2<i32>) {
+        let player_position = self.player_positions[player];
+        self.map[player_position].remove(Tiles::Player);
+        self.map[position].insert(Tiles::Player);
+        self.player_positions[player] = position;
+    }
+}
+
+impl FromStr for MultiMap {
+    type Err = ParseMapError;
+
+    /// Creates a new `MultiMap` from XSB format string.
+    ///
+    /// Unlike [`Map`], the map may contain any number of players, but at least
+    /// one.
+    fn from_str(xsb: &str) -> Result<Self, Self::Err> {
+        let (map, player_positions, _) = Map::parse(xsb, true)?;
+        Ok(Self {
+            map,
+            player_positions,
+        })
+    }
+}
+
+impl fmt::Display for MultiMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.map)
+    }
+}
+
+impl Index<Vector2<i32>> for MultiMap {
+    type Output = Tiles;
+
+    fn index(&self, position: Vector2<i32>) -> &Tiles {
+        &self.map[position]
+    }
+}
+
+impl IndexMut<Vector2<i32>> for MultiMap {
+    fn index_mut(&mut self, position: Vector2<i32>) -> &mut Tiles {
+        &mut self.map[position]
+    }
+}
+
+/// An action performed by one of the players.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct MultiAction {
+    /// The index of the player, counting from 0.
+    pub player: usize,
+    /// The action performed by the player.
+    pub action: Action,
+}
+
+/// A owned, mutable multi-player actions (akin to [`Vec<MultiAction>`]).
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct MultiActions(pub Vec<MultiAction>);
+
+impl MultiActions {
+    /// Creates an empty actions.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the number of moves.
+    pub fn moves(&self) -> usize {
+        self.len()
+    }
+
+    /// Returns the number of pushes.
+    pub fn pushes(&self) -> usize {
+        self.iter().filter(|action| action.action.is_push()).count()
+    }
+
+    /// Returns the actions performed by the player with the index.
+    pub fn of_player(&self, player: usize) -> Actions {
+        Actions(
+            self.iter()
+                .filter(|action| action.player == player)
+                .map(|action| action.action)
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for MultiActions {
+    type Err = ParseActionsError;
+
+    /// Creates a new `MultiActions` with multi-player LURD format string.
+    fn from_str(lurd: &str) -> Result<Self, Self::Err> {
+        let mut instance = MultiActions::default();
+        let mut segments = lurd.split('[');
+        let mut push_segment = |player, segment: &str| -> Result<(), Self::Err> {
+            for action in Actions::from_str(segment)?.0 {
+                instance.push(MultiAction { player, action });
+            }
+            Ok(())
+        };
+        push_segment(0, segments.next().unwrap_or_default())?;
+        for segment in segments {
+            let (player, segment) = segment
+                .split_once(']')
+                .ok_or(ParseActionsError::InvalidPlayerMarker)?;
+            let player: usize = player
+                .parse()
+                .map_err(|_| ParseActionsError::InvalidPlayerMarker)?;
+            if player == 0 {
+                return Err(ParseActionsError::InvalidPlayerMarker);
+            }
+            push_segment(player - 1, segment)?;
+        }
+        Ok(instance)
+    }
+}
+
+impl Deref for MultiActions {
+    type Target = Vec<MultiAction>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for MultiActions {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for MultiActions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut current_player = 0;
+        for action in &self.0 {
+            if action.player != current_player {
+                write!(f, "[{}]", action.player + 1)?;
+                current_player = action.player;
+            }
+            write!(f, "{}", action.action)?;
+        }
+        Ok(())
+    }
+}
+
+/// A Multiban level.
+///
+/// `MultiLevel` is separate from [`Level`](crate::Level), since its actions
+/// record which player performed them and its map has several players, which
+/// [`Action`] and [`Map`] can not represent. It provides the same branching
+/// undo and redo as `Level`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MultiLevel {
+    map: MultiMap,
+    actions: MultiActions,
+    undo_tree: UndoTree<MultiActions>,
+}
+
+impl MultiLevel {
+    /// Creates a new `MultiLevel` from a map.
+    pub fn from_map(map: MultiMap) -> Self {
+        Self {
+            map,
+            actions: MultiActions::new(),
+            undo_tree: UndoTree::default(),
+        }
+    }
+
+    /// Returns a reference to the map of the level.
+    pub fn map(&self) -> &MultiMap {
+        &self.map
+    }
+
+    /// Returns a reference to the actions performed on the level.
+    pub fn actions(&self) -> &MultiActions {
+        &self.actions
+    }
+
+    /// Performs a sequence of actions on the level.
+    ///
+    /// Only the directions of the actions are used, whether a box is pushed
+    /// is determined by the map.
+    pub fn do_actions<'a, I: IntoIterator<Item = &'a MultiAction>>(
+        &mut self,
+        actions: I,
+    ) -> Result<(), ActionError> {
+        for action in actions {
+            self.do_action(action.player, action.action.direction())?;
+        }
+        Ok(())
+    }
+
+    /// Moves the player with the index in the specified direction.
+    ///
+    /// Returns the events caused by the action.
+    pub fn do_action(
+        &mut self,
+        player: usize,
+        direction: Direction,
+    ) -> Result<Vec<Event>, ActionError> {
+        let mut events = Vec::new();
+        let action = self.perform_action(player, direction, &mut events)?;
+        let action = MultiAction { player, action };
+        self.actions.push(action);
+        self.undo_tree.push(action);
+        if self.map.is_solved() {
+            events.push(Event::Solved);
+        }
+        Ok(events)
+    }
+
+    /// Undoes the last action.
+    ///
+    /// Returns the events caused by undoing the action.
+    pub fn undo_action(&mut self) -> Result<Vec<Event>, ActionError> {
+        let last_actions = self.undo_tree.undo().ok_or(ActionError::NoActions)?;
+        let mut events = Vec::new();
+        for &MultiAction { player, action } in last_actions.iter().rev() {
+            self.actions.pop();
+            events.push(Event::Undone(action));
+            let player_position = self.map.player_positions[player];
+            let prev_player_position = player_position - &action.direction().into();
+            self.map.set_player_position(player, prev_player_position);
+            events.push(Event::PlayerMoved {
+                from: player_position,
+                to: prev_player_position,
+            });
+            if action.is_push() {
+                let box_position = player_position + &action.direction().into();
+                self.map.map.set_box_position(box_position, player_position);
+                self.box_events(box_position, player_position, &mut events);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Redoes the last undone action.
+    ///
+    /// Returns the events caused by redoing the action. If there are multiple
+    /// branches, the most recent one is followed unless another branch is
+    /// selected with [`switch_branch`].
+    ///
+    /// [`switch_branch`]: MultiLevel::switch_branch
+    pub fn redo_action(&mut self) -> Result<Vec<Event>, ActionError> {
+        let undone_actions = self.undo_tree.redo().ok_or(ActionError::NoUndoneActions)?;
+        let mut events = Vec::new();
+        for &undone_action in undone_actions.iter() {
+            events.push(Event::Redone(undone_action.action));
+            self.perform_action(
+                undone_action.player,
+                undone_action.action.direction(),
+                &mut events,
+            )
+            .unwrap();
+            self.actions.push(undone_action);
+        }
+        Ok(events)
+    }
+
+    /// Returns a reference to the undo tree of the level.
+    pub fn undo_tree(&self) -> &UndoTree<MultiActions> {
+        &self.undo_tree
+    }
+
+    /// Switches the branch followed by [`redo_action`].
+    ///
+    /// The branches at the current position are listed by
+    /// [`UndoTree::branches`].
+    ///
+    /// [`redo_action`]: MultiLevel::redo_action
+    pub fn switch_branch(&mut self, index: usize) -> Result<(), ActionError> {
+        if self.undo_tree.switch_branch(index) {
+            Ok(())
+        } else {
+            Err(ActionError::NoBranch)
+        }
+    }
+
+    /// Checks if the level is solved.
+    pub fn is_solved(&self) -> bool {
+        self.map.is_solved()
+    }
+
+    /// Moves the player with the index in the specified direction without
+    /// recording the action, and returns the performed action.
+    fn perform_action(
+        &mut self,
+        player: usize,
+        direction: Direction,
+        events: &mut Vec<Event>,
+    ) -> Result<Action, ActionError> {
+        let player_position = *self
+            .map
+            .player_positions()
+            .get(player)
+            .ok_or(ActionError::InvalidPlayer(player))?;
+        let action = self.map.do_move(player, direction)?;
+        let new_player_position = self.map.player_positions[player];
+        events.push(Event::PlayerMoved {
+            from: player_position,
+            to: new_player_position,
+        });
+        if action.is_push() {
+            let new_box_position = new_player_position + &direction.into();
+            self.box_events(new_player_position, new_box_position, events);
+        }
+        Ok(action)
+    }
+
+    fn box_events(&self, from: Vector2<i32>, to: Vector2<i32>, events: &mut Vec<Event>) {
+        events.push(Event::BoxMoved { from, to });
+        if self.map.goal_positions().contains(&from) {
+            events.push(Event::BoxLeftGoal(from));
+        }
+        if self.map.goal_positions().contains(&to) {
+            events.push(Event::BoxLandedOnGoal(to));
+        }
+    }
+}
+
+impl FromStr for MultiLevel {
+    type Err = ParseMapError;
+
+    /// Creates a new `MultiLevel` from XSB format string.
+    fn from_str(xsb: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_map(MultiMap::from_str(xsb)?))
+    }
+}
+
+/// A state of the search of [`MultiSolver`].
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub(crate) struct MultiState {
+    player_positions: Vec<Vector2<i32>>,
+    /// The positions of the boxes, sorted in reading order.
+    box_positions: Vec<Vector2<i32>>,
+}
+
+/// A move optimal solver for Multiban.
+///
+/// The choice of the player is part of the search state, so each step of the
+/// search is an action of any one of the players. The search is shared with
+/// [`GridSolver`](crate::solver::GridSolver).
+#[derive(Clone, Debug)]
+pub struct MultiSolver {
+    map: MultiMap,
+    lower_bounds: HashMap<Vector2<i32>, i32>,
+    node_limit: Option<usize>,
+    expanded_nodes: Cell<usize>,
+}
+
+impl MultiSolver {
+    /// Creates a new `MultiSolver`.
+    pub fn new(map: MultiMap) -> Self {
+        let is_floor = |position| {
+            map.get(position)
+                .is_some_and(|tiles: &Tiles| tiles.intersects(Tiles::Floor))
+        };
+        let lower_bounds = box_move_distances::<Square>(
+            map.goal_positions().iter().copied(),
+            MovementRules::PushOnly,
+            is_floor,
+        );
+        Self {
+            map,
+            lower_bounds,
+            node_limit: None,
+            expanded_nodes: Cell::new(0),
+        }
+    }
+
+    /// Sets the maximum number of nodes expanded by [`a_star_search`].
+    ///
+    /// [`a_star_search`]: MultiSolver::a_star_search
+    pub fn set_node_limit(&mut self, node_limit: Option<usize>) {
+        self.node_limit = node_limit;
+    }
+
+    /// Returns a reference to the map.
+    pub fn map(&self) -> &MultiMap {
+        &self.map
+    }
+
+    /// Returns the number of nodes expanded by the last [`a_star_search`].
+    ///
+    /// [`a_star_search`]: MultiSolver::a_star_search
+    pub fn expanded_nodes(&self) -> usize {
+        self.expanded_nodes.get()
+    }
+
+    /// Searches for a move optimal solution using the A* algorithm.
+    pub fn a_star_search(&self) -> Result<MultiActions, SearchError> {
+        let mut box_positions: Vec<_> = self.map.box_positions().iter().copied().collect();
+        box_positions.sort_by_key(|position| (position.y, position.x));
+        let state = MultiState {
+            player_positions: self.map.player_positions().to_vec(),
+            box_positions,
+        };
+        a_star_search(self, state, self.node_limit, &self.expanded_nodes).map(MultiActions)
+    }
+}
+
+impl SearchProblem for MultiSolver {
+    type State = MultiState;
+    type Key = MultiState;
+    type Step = MultiAction;
+
+    fn key(&self, state: &MultiState) -> MultiState {
+        state.clone()
+    }
+
+    /// Returns the sum of the lower bounds of the boxes, or `None` if any box
+    /// is in a static deadlock.
+    fn heuristic(&self, state: &MultiState) -> Option<i32> {
+        state
+            .box_positions
+            .iter()
+            .map(|position| self.lower_bounds.get(position))
+            .sum()
+    }
+
+    fn is_goal(&self, state: &MultiState) -> bool {
+        state
+            .box_positions
+            .iter()
+            .all(|position| self.map.goal_positions().contains(position))
+    }
+
+    fn successors(&self, state: &MultiState) -> Vec<(MultiAction, MultiState)> {
+        let is_free = |position: Vector2<i32>| {
+            self.map
+                .get(position)
+                .is_some_and(|tiles| tiles.intersects(Tiles::Floor))
+                && !state.player_positions.contains(&position)
+                && !state.box_positions.contains(&position)
+        };
+        let mut successors = Vec::new();
+        for (player, &player_position) in state.player_positions.iter().enumerate() {
+            for direction in Direction::iter() {
+                let new_player_position = player_position + &direction.into();
+                let mut box_positions = state.box_positions.clone();
+                let action = if let Some(box_index) = box_positions
+                    .iter()
+                    .position(|&position| position == new_player_position)
+                {
+                    let new_box_position = new_player_position + &direction.into();
+                    if !is_free(new_box_position) {
+                        continue;
+                    }
+                    box_positions[box_index] = new_box_position;
+                    box_positions.sort_by_key(|position| (position.y, position.x));
+                    Action::Push(direction)
+                } else if is_free(new_player_position) {
+                    Action::Move(direction)
+                } else {
+                    continue;
+                };
+                let mut player_positions = state.player_positions.clone();
+                player_positions[player] = new_player_position;
+                successors.push((
+                    MultiAction { player, action },
+                    MultiState {
+                        player_positions,
+                        box_positions,
+                    },
+                ));
+            }
+        }
+        successors
+    }
+}
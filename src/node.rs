@@ -1,9 +1,13 @@
 use std::{cmp::Ordering, collections::HashSet};
 
+use itertools::Itertools;
+use nalgebra::Vector2;
+
 use crate::{
     deadlock::is_freeze_deadlock,
     direction::Direction,
     path_finding::{find_path, reachable_area},
+    rules::MovementRules,
     solver::{Solver, Strategy},
     state::State,
     Tiles,
@@ -42,80 +46,176 @@ impl Node {
             !solver.map()[position].intersects(Tiles::Wall)
                 && !self.state.box_positions.contains(&position)
         });
-        // Creates successor states by pushing boxes
-        for box_position in &self.state.box_positions {
-            for push_direction in Direction::iter() {
-                let mut new_box_position = box_position + &push_direction.into();
-
-                // Checks if the box can be pushed
-                if solver.map()[new_box_position].intersects(Tiles::Wall)
-                    || self.state.box_positions.contains(&new_box_position)
-                    || !solver.lower_bounds().contains_key(&new_box_position)
-                {
-                    continue;
+        // Creates successor states by pushing and pulling boxes, in a fixed
+        // order so that the search is deterministic
+        let box_positions = self
+            .state
+            .box_positions
+            .iter()
+            .sorted_by_key(|position| (position.y, position.x));
+        for box_position in box_positions {
+            for direction in Direction::iter() {
+                if solver.movement_rules().can_push() {
+                    successors.extend(self.push_successor(
+                        solver,
+                        *box_position,
+                        direction,
+                        &player_reachable_area,
+                    ));
                 }
-
-                // Checks if the player can push the box
-                if !player_reachable_area.contains(&(box_position - &push_direction.into())) {
-                    continue;
+                if solver.movement_rules().can_pull() {
+                    successors.extend(self.pull_successor(
+                        solver,
+                        *box_position,
+                        direction,
+                        &player_reachable_area,
+                    ));
                 }
+            }
+        }
+        successors
+    }
 
-                let mut new_player_position = *box_position;
-
-                let mut new_pushes = self.pushes + 1;
-                let mut new_moves = self.moves
-                    + find_path(
-                        self.state.player_position,
-                        new_player_position,
-                        |position| {
-                            !solver.map()[position].intersects(Tiles::Wall)
-                                && (!self.state.box_positions.contains(&position)
-                                    || position == *box_position)
-                        },
-                    )
-                    .unwrap()
-                    .len() as i32
-                    - 1;
-
-                // Skip no influence pushes
-                while solver
-                    .tunnels()
-                    .contains(&(new_box_position, push_direction))
-                {
-                    new_player_position = new_box_position;
-                    new_box_position += &push_direction.into();
-                    new_pushes += 1;
-                    new_moves += 1;
-                }
+    /// Returns the successor created by pushing the box in the direction, if
+    /// the push is possible.
+    fn push_successor(
+        &self,
+        solver: &Solver,
+        box_position: Vector2<i32>,
+        push_direction: Direction,
+        player_reachable_area: &HashSet<Vector2<i32>>,
+    ) -> Option<Node> {
+        let mut new_box_position = box_position + &push_direction.into();
+        let box_id = self.state.box_id(box_position);
 
-                let mut new_box_positions = self.state.box_positions.clone();
-                new_box_positions.remove(box_position);
-                new_box_positions.insert(new_box_position);
-
-                // Skip freeze deadlocks
-                if !solver.map()[new_box_position].intersects(Tiles::Goal)
-                    && is_freeze_deadlock(
-                        solver.map(),
-                        new_box_position,
-                        &new_box_positions,
-                        &mut HashSet::new(),
-                    )
-                {
-                    continue;
-                }
+        // Checks if the box can be pushed
+        if solver.map()[new_box_position].intersects(Tiles::Wall)
+            || self.state.box_positions.contains(&new_box_position)
+            || solver.lower_bound(new_box_position, box_id).is_none()
+        {
+            return None;
+        }
 
-                successors.push(Node::new(
-                    State {
-                        player_position: new_player_position,
-                        box_positions: new_box_positions,
-                    },
-                    new_pushes,
-                    new_moves,
-                    solver,
-                ));
-            }
+        // Checks if the player can push the box
+        if !player_reachable_area.contains(&(box_position - &push_direction.into())) {
+            return None;
         }
-        successors
+
+        let mut new_player_position = box_position;
+
+        let mut new_pushes = self.pushes + 1;
+        let mut new_moves = self.moves
+            + find_path(
+                self.state.player_position,
+                new_player_position,
+                |position| {
+                    !solver.map()[position].intersects(Tiles::Wall)
+                        && (!self.state.box_positions.contains(&position)
+                            || position == box_position)
+                },
+            )
+            .unwrap()
+            .len() as i32
+            - 1;
+
+        // Skip no influence pushes
+        while solver
+            .tunnels()
+            .contains(&(new_box_position, push_direction))
+        {
+            new_player_position = new_box_position;
+            new_box_position += &push_direction.into();
+            new_pushes += 1;
+            new_moves += 1;
+        }
+
+        // Checks if the box is pushed through a tunnel into a deadlock
+        solver.lower_bound(new_box_position, box_id)?;
+
+        let mut new_box_positions = self.state.box_positions.clone();
+        new_box_positions.remove(&box_position);
+        new_box_positions.insert(new_box_position);
+        let mut new_box_ids = self.state.box_ids.clone();
+        if let Some(id) = new_box_ids.remove(&box_position) {
+            new_box_ids.insert(new_box_position, id);
+        }
+
+        // Skip freeze deadlocks, including boxes frozen on goals with
+        // other IDs
+        if solver.movement_rules() == MovementRules::PushOnly
+            && !(solver.map()[new_box_position].intersects(Tiles::Goal)
+                && solver.map().goal_id(new_box_position) == box_id)
+            && is_freeze_deadlock(
+                solver.map(),
+                new_box_position,
+                &new_box_positions,
+                &mut HashSet::new(),
+            )
+        {
+            return None;
+        }
+
+        Some(Node::new(
+            State {
+                player_position: new_player_position,
+                box_positions: new_box_positions,
+                box_ids: new_box_ids,
+            },
+            new_pushes,
+            new_moves,
+            solver,
+        ))
+    }
+
+    /// Returns the successor created by pulling the box in the direction, if
+    /// the pull is possible.
+    fn pull_successor(
+        &self,
+        solver: &Solver,
+        box_position: Vector2<i32>,
+        pull_direction: Direction,
+        player_reachable_area: &HashSet<Vector2<i32>>,
+    ) -> Option<Node> {
+        let new_box_position = box_position + &pull_direction.into();
+        let new_player_position = new_box_position + &pull_direction.into();
+        let box_id = self.state.box_id(box_position);
+
+        // Checks if the player can pull the box
+        if !player_reachable_area.contains(&new_box_position)
+            || solver.map()[new_player_position].intersects(Tiles::Wall)
+            || self.state.box_positions.contains(&new_player_position)
+            || solver.lower_bound(new_box_position, box_id).is_none()
+        {
+            return None;
+        }
+
+        // The moves to reach the box, and the pull itself
+        let new_moves = self.moves
+            + find_path(self.state.player_position, new_box_position, |position| {
+                !solver.map()[position].intersects(Tiles::Wall)
+                    && !self.state.box_positions.contains(&position)
+            })
+            .unwrap()
+            .len() as i32;
+
+        let mut new_box_positions = self.state.box_positions.clone();
+        new_box_positions.remove(&box_position);
+        new_box_positions.insert(new_box_position);
+        let mut new_box_ids = self.state.box_ids.clone();
+        if let Some(id) = new_box_ids.remove(&box_position) {
+            new_box_ids.insert(new_box_position, id);
+        }
+
+        Some(Node::new(
+            State {
+                player_position: new_player_position,
+                box_positions: new_box_positions,
+                box_ids: new_box_ids,
+            },
+            self.pushes + 1,
+            new_moves,
+            solver,
+        ))
     }
 }
 
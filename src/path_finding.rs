@@ -1,13 +1,23 @@
 //! Utilities for path finding.
 
 use std::{
-    cmp::Ordering,
+    cmp::{Ordering, Reverse},
     collections::{BinaryHeap, HashMap, HashSet, VecDeque},
 };
 
+use itertools::Itertools;
 use nalgebra::Vector2;
 
-use crate::{direction::Direction, map::Map, Tiles};
+use crate::{
+    action::Action,
+    actions::Actions,
+    direction::Direction,
+    error::SearchError,
+    map::Map,
+    rules::MovementRules,
+    topology::{Square, Topology},
+    Tiles,
+};
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
 struct Node {
@@ -27,6 +37,234 @@ impl PartialOrd for Node {
     }
 }
 
+/// The metric to optimize when planning the actions to push a box.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Optimization {
+    /// Minimizes the number of moves, with the number of pushes as a
+    /// tie-breaker.
+    #[default]
+    Moves,
+    /// Minimizes the number of pushes, with the number of moves as a
+    /// tie-breaker.
+    Pushes,
+}
+
+/// The position of the box and the position of the player.
+type BoxState = (Vector2<i32>, Vector2<i32>);
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct BoxNode {
+    box_position: Vector2<i32>,
+    player_position: Vector2<i32>,
+    /// The primary and secondary costs, depending on the optimization.
+    cost: (i32, i32),
+}
+
+impl Ord for BoxNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cost.cmp(&other.cost).reverse()
+    }
+}
+
+impl PartialOrd for BoxNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+struct BoxesNode {
+    box_positions: Vec<Vector2<i32>>,
+    player_position: Vector2<i32>,
+    /// The index of the parent node, the index of the pushed box and the push
+    /// direction.
+    parent: Option<(usize, usize, Direction)>,
+}
+
+/// A table of the distances between floor positions for the player.
+///
+/// The distances only take walls into account and are calculated once, so
+/// each lookup takes constant time.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct DistanceTable {
+    dimensions: Vector2<i32>,
+    indices: Vec<Option<usize>>,
+    len: usize,
+    distances: Vec<i32>,
+}
+
+impl DistanceTable {
+    /// Creates a new `DistanceTable` for the map.
+    pub fn new(map: &Map) -> Self {
+        let dimensions = map.dimensions();
+        let mut indices = vec![None; (dimensions.x * dimensions.y) as usize];
+        let mut positions = Vec::new();
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                let position = Vector2::new(x, y);
+                if is_floor(map, position) {
+                    indices[(y * dimensions.x + x) as usize] = Some(positions.len());
+                    positions.push(position);
+                }
+            }
+        }
+
+        let mut table = Self {
+            dimensions,
+            indices,
+            len: positions.len(),
+            distances: vec![i32::MAX; positions.len() * positions.len()],
+        };
+        for (index, &position) in positions.iter().enumerate() {
+            let offset = index * positions.len();
+            for (position, distance) in
+                distances::<Square>(position, |position| is_floor(map, position))
+            {
+                let to = table.index(position).unwrap();
+                table.distances[offset + to] = distance;
+            }
+        }
+        table
+    }
+
+    /// Returns the distance between two positions, or `None` if one position
+    /// is not reachable from the other.
+    pub fn distance(&self, from: Vector2<i32>, to: Vector2<i32>) -> Option<i32> {
+        let (from, to) = (self.index(from)?, self.index(to)?);
+        let distance = self.distances[from * self.len + to];
+        (distance != i32::MAX).then_some(distance)
+    }
+
+    /// Returns the index of the floor position.
+    fn index(&self, position: Vector2<i32>) -> Option<usize> {
+        if !(0..self.dimensions.x).contains(&position.x)
+            || !(0..self.dimensions.y).contains(&position.y)
+        {
+            return None;
+        }
+        self.indices[(position.y * self.dimensions.x + position.x) as usize]
+    }
+}
+
+/// A table of the minimum number of pushes required to push a box from each
+/// position to each goal.
+///
+/// The pushes only take walls into account, other boxes are ignored. The
+/// minimum distances are the lower bounds used by the solver, see
+/// [`Solver::lower_bounds`].
+///
+/// [`Solver::lower_bounds`]: crate::solver::Solver::lower_bounds
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PushDistanceTable {
+    distances: HashMap<Vector2<i32>, HashMap<Vector2<i32>, i32>>,
+}
+
+impl PushDistanceTable {
+    /// Creates a new `PushDistanceTable` for the map.
+    pub fn new(map: &Map) -> Self {
+        let distances = map
+            .goal_positions()
+            .iter()
+            .map(|&goal_position| {
+                let distances = box_move_distances::<Square>(
+                    [goal_position],
+                    MovementRules::PushOnly,
+                    |position| is_floor(map, position),
+                );
+                (goal_position, distances)
+            })
+            .collect();
+        Self { distances }
+    }
+
+    /// Returns the minimum number of pushes required to push a box from the
+    /// position to the goal, or `None` if it is impossible.
+    pub fn distance(&self, box_position: Vector2<i32>, goal_position: Vector2<i32>) -> Option<i32> {
+        self.distances
+            .get(&goal_position)?
+            .get(&box_position)
+            .copied()
+    }
+
+    /// Returns the minimum number of pushes required to push a box from the
+    /// position to the nearest goal, or `None` if it is impossible.
+    pub fn minimum_distance(&self, box_position: Vector2<i32>) -> Option<i32> {
+        self.distances
+            .values()
+            .filter_map(|distances| distances.get(&box_position))
+            .min()
+            .copied()
+    }
+}
+
+/// Calculates the minimum number of box moves required to move a box from each
+/// position to the nearest of the goals under the movement rules, ignoring
+/// other boxes.
+///
+/// The box is placed on the goals and moved backwards in breadth-first order,
+/// taking the positions reachable by the player into account. Under push-only
+/// rules, the distances are the minimum numbers of pushes.
+pub(crate) fn box_move_distances<T: Topology>(
+    goal_positions: impl IntoIterator<Item = Vector2<i32>>,
+    rules: MovementRules,
+    is_floor: impl Fn(Vector2<i32>) -> bool,
+) -> HashMap<Vector2<i32>, i32> {
+    let mut distances = HashMap::new();
+    // The position of the box and the direction of the player from the box
+    let mut visited = HashSet::new();
+    let mut deque = VecDeque::new();
+    for goal_position in goal_positions {
+        distances.insert(goal_position, 0);
+        // Before the first move, the player can be on any side of the box
+        for &direction in T::directions() {
+            if is_floor(T::neighbor(goal_position, direction))
+                && visited.insert((goal_position, direction))
+            {
+                deque.push_back((goal_position, direction, 0));
+            }
+        }
+    }
+
+    while let Some((box_position, direction, distance)) = deque.pop_front() {
+        let player_reachable_area =
+            reachable_area_in::<T>(T::neighbor(box_position, direction), |position| {
+                position != box_position && is_floor(position)
+            });
+        for &direction in T::directions() {
+            let new_box_position = T::neighbor(box_position, direction);
+            if !is_floor(new_box_position) {
+                continue;
+            }
+            let mut new_states = Vec::with_capacity(2);
+            // Undo a push by pulling the box away from the goal
+            if rules.can_push()
+                && player_reachable_area.contains(&new_box_position)
+                && is_floor(T::neighbor(new_box_position, direction))
+            {
+                new_states.push((new_box_position, direction));
+            }
+            // Undo a pull by pushing the box away from the goal
+            if rules.can_pull()
+                && player_reachable_area.contains(&T::neighbor(box_position, -direction))
+            {
+                new_states.push((new_box_position, -direction));
+            }
+            for new_state in new_states {
+                if visited.insert(new_state) {
+                    distances.entry(new_box_position).or_insert(distance + 1);
+                    deque.push_back((new_state.0, new_state.1, distance + 1));
+                }
+            }
+        }
+    }
+
+    distances
+}
+
+/// Returns `true` if the position is a floor of the map.
+fn is_floor(map: &Map, position: Vector2<i32>) -> bool {
+    map.in_bounds(position) && map[position].intersects(Tiles::Floor)
+}
+
 /// Finds a path from one position to another on the map.
 ///
 /// This function uses the A* algorithm to find the shortest path from the
@@ -36,6 +274,18 @@ pub fn find_path(
     from: Vector2<i32>,
     to: Vector2<i32>,
     can_move: impl Fn(Vector2<i32>) -> bool,
+) -> Option<Vec<Vector2<i32>>> {
+    find_path_in::<Square>(from, to, can_move)
+}
+
+/// Finds a path from one position to another on a grid with the given
+/// topology.
+///
+/// See [`find_path`] for details.
+pub fn find_path_in<T: Topology>(
+    from: Vector2<i32>,
+    to: Vector2<i32>,
+    can_move: impl Fn(Vector2<i32>) -> bool,
 ) -> Option<Vec<Vector2<i32>>> {
     let mut open_set = BinaryHeap::new();
     let mut came_from = HashMap::new();
@@ -43,7 +293,7 @@ pub fn find_path(
 
     open_set.push(Node {
         position: from,
-        heuristic: manhattan_distance(from, to),
+        heuristic: T::distance(from, to),
     });
     cost.insert(from, 0);
 
@@ -52,8 +302,8 @@ pub fn find_path(
             return Some(construct_path(from, to, came_from));
         }
 
-        for direction in Direction::iter() {
-            let new_position = node.position + &direction.into();
+        for &direction in T::directions() {
+            let new_position = T::neighbor(node.position, direction);
             if !can_move(new_position) {
                 continue;
             }
@@ -61,7 +311,7 @@ pub fn find_path(
             let new_cost = cost[&node.position] + 1;
             if !cost.contains_key(&new_position) || new_cost < cost[&new_position] {
                 cost.insert(new_position, new_cost);
-                let priority = new_cost + manhattan_distance(new_position, to);
+                let priority = new_cost + T::distance(new_position, to);
                 open_set.push(Node {
                     position: new_position,
                     heuristic: priority,
@@ -216,6 +466,256 @@ pub fn construct_box_path(
     path
 }
 
+/// Calculates the actions for the player to push a box from its current
+/// position to a target position.
+///
+/// The other boxes are treated as obstacles. The actions are optimal according
+/// to the specified optimization. Returns `None` if the box can not be pushed
+/// to the target position.
+pub fn box_move_actions(
+    map: &Map,
+    from: Vector2<i32>,
+    to: Vector2<i32>,
+    optimization: Optimization,
+) -> Option<Actions> {
+    debug_assert!(
+        map.box_positions().contains(&from),
+        "box position does not exist"
+    );
+    let can_move = |position: Vector2<i32>| position == from || map.can_move(position);
+
+    let mut open_set = BinaryHeap::new();
+    let mut came_from = HashMap::new();
+    let mut cost = HashMap::new();
+
+    let initial_state = (from, map.player_position());
+    open_set.push(BoxNode {
+        box_position: from,
+        player_position: map.player_position(),
+        cost: (0, 0),
+    });
+    cost.insert(initial_state, (0, 0));
+
+    while let Some(node) = open_set.pop() {
+        let state = (node.box_position, node.player_position);
+        if node.cost > cost[&state] {
+            continue;
+        }
+        if node.box_position == to {
+            return Some(construct_box_move_actions(
+                initial_state,
+                state,
+                &came_from,
+                can_move,
+            ));
+        }
+
+        let player_distances = distances::<Square>(node.player_position, |position| {
+            position != node.box_position && can_move(position)
+        });
+        for push_direction in Direction::iter() {
+            let new_box_position = node.box_position + &push_direction.into();
+            if !can_move(new_box_position) {
+                continue;
+            }
+            let Some(distance) =
+                player_distances.get(&(node.box_position - &push_direction.into()))
+            else {
+                continue;
+            };
+
+            let new_state = (new_box_position, node.box_position);
+            let new_cost = match optimization {
+                Optimization::Moves => (node.cost.0 + distance + 1, node.cost.1 + 1),
+                Optimization::Pushes => (node.cost.0 + 1, node.cost.1 + distance + 1),
+            };
+            if cost.get(&new_state).is_some_and(|&cost| cost <= new_cost) {
+                continue;
+            }
+            cost.insert(new_state, new_cost);
+            came_from.insert(new_state, state);
+            open_set.push(BoxNode {
+                box_position: new_box_position,
+                player_position: node.box_position,
+                cost: new_cost,
+            });
+        }
+    }
+
+    None
+}
+
+/// Constructs the actions from the box states found by [`box_move_actions`].
+fn construct_box_move_actions(
+    initial_state: BoxState,
+    mut state: BoxState,
+    came_from: &HashMap<BoxState, BoxState>,
+    can_move: impl Fn(Vector2<i32>) -> bool,
+) -> Actions {
+    let mut states = vec![state];
+    while state != initial_state {
+        state = came_from[&state];
+        states.push(state);
+    }
+    states.reverse();
+
+    let mut actions = Actions::new();
+    for states in states.windows(2) {
+        let ((box_position, player_position), (new_box_position, _)) = (states[0], states[1]);
+        let push_direction = Direction::try_from(new_box_position - box_position).unwrap();
+        let path = find_path(
+            player_position,
+            box_position - &push_direction.into(),
+            |position| position != box_position && can_move(position),
+        )
+        .unwrap();
+        actions.extend(
+            convert_path_from_points_to_directions(path)
+                .into_iter()
+                .map(Action::Move),
+        );
+        actions.push(Action::Push(push_direction));
+    }
+    actions
+}
+
+/// Calculates the actions for the player to push several boxes to their
+/// target positions.
+///
+/// `targets` maps the current positions of the boxes to their target
+/// positions. Unlike [`box_move_actions`], the other boxes are not treated as
+/// obstacles and may be pushed aside. The actions are push optimal.
+///
+/// The search expands at most `limit` states. Returns
+/// [`SearchError::LimitExceeded`] if the limit is reached, or
+/// [`SearchError::NoSolution`] if the boxes can not be pushed to the target
+/// positions.
+pub fn boxes_move_actions(
+    map: &Map,
+    targets: &HashMap<Vector2<i32>, Vector2<i32>>,
+    limit: usize,
+) -> Result<Actions, SearchError> {
+    debug_assert!(
+        targets
+            .keys()
+            .all(|position| map.box_positions().contains(position)),
+        "box position does not exist"
+    );
+    let is_floor = |position| is_floor(map, position);
+
+    // The tracked boxes come first, followed by the other boxes in sorted order
+    let (box_positions, target_positions): (Vec<_>, Vec<_>) = targets
+        .iter()
+        .map(|(&from, &to)| (from, to))
+        .sorted_by_key(|(from, _)| (from.y, from.x))
+        .unzip();
+    let mut initial_box_positions = box_positions;
+    initial_box_positions.extend(
+        map.box_positions()
+            .iter()
+            .filter(|position| !targets.contains_key(position))
+            .sorted_by_key(|position| (position.y, position.x)),
+    );
+    let heuristic = |box_positions: &[Vector2<i32>]| {
+        box_positions
+            .iter()
+            .zip(&target_positions)
+            .map(|(&from, &to)| Square::distance(from, to))
+            .sum::<i32>()
+    };
+
+    let mut nodes = vec![BoxesNode {
+        box_positions: initial_box_positions.clone(),
+        player_position: map.player_position(),
+        parent: None,
+    }];
+    let mut visited = HashSet::new();
+    let mut open_set = BinaryHeap::new();
+    open_set.push(Reverse((heuristic(&initial_box_positions), 0, 0)));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, pushes, index))) = open_set.pop() {
+        let box_positions = nodes[index].box_positions.clone();
+        let player_reachable_area = reachable_area(nodes[index].player_position, |position| {
+            is_floor(position) && !box_positions.contains(&position)
+        });
+        let normalized_player_position = normalized_area(&player_reachable_area).unwrap();
+        if !visited.insert((box_positions.clone(), normalized_player_position)) {
+            continue;
+        }
+        if heuristic(&box_positions) == 0 {
+            return Ok(construct_boxes_move_actions(map, &nodes, index, is_floor));
+        }
+        expanded += 1;
+        if expanded > limit {
+            return Err(SearchError::LimitExceeded);
+        }
+
+        for (box_index, &box_position) in box_positions.iter().enumerate() {
+            for push_direction in Direction::iter() {
+                let new_box_position = box_position + &push_direction.into();
+                if !is_floor(new_box_position) || box_positions.contains(&new_box_position) {
+                    continue;
+                }
+                if !player_reachable_area.contains(&(box_position - &push_direction.into())) {
+                    continue;
+                }
+                let mut new_box_positions = box_positions.clone();
+                new_box_positions[box_index] = new_box_position;
+                if box_index >= target_positions.len() {
+                    new_box_positions[target_positions.len()..]
+                        .sort_by_key(|position| (position.y, position.x));
+                }
+                let priority = pushes + 1 + heuristic(&new_box_positions);
+                nodes.push(BoxesNode {
+                    box_positions: new_box_positions,
+                    player_position: box_position,
+                    parent: Some((index, box_index, push_direction)),
+                });
+                open_set.push(Reverse((priority, pushes + 1, nodes.len() - 1)));
+            }
+        }
+    }
+
+    Err(SearchError::NoSolution)
+}
+
+/// Constructs the actions from the nodes found by [`boxes_move_actions`].
+fn construct_boxes_move_actions(
+    map: &Map,
+    nodes: &[BoxesNode],
+    mut index: usize,
+    is_floor: impl Fn(Vector2<i32>) -> bool,
+) -> Actions {
+    let mut pushes = Vec::new();
+    while let Some((parent, box_index, push_direction)) = nodes[index].parent {
+        pushes.push((parent, box_index, push_direction));
+        index = parent;
+    }
+    pushes.reverse();
+
+    let mut actions = Actions::new();
+    let mut player_position = map.player_position();
+    for (parent, box_index, push_direction) in pushes {
+        let box_positions = &nodes[parent].box_positions;
+        let box_position = box_positions[box_index];
+        let path = find_path(
+            player_position,
+            box_position - &push_direction.into(),
+            |position| is_floor(position) && !box_positions.contains(&position),
+        )
+        .unwrap();
+        actions.extend(
+            convert_path_from_points_to_directions(path)
+                .into_iter()
+                .map(Action::Move),
+        );
+        actions.push(Action::Push(push_direction));
+        player_position = box_position;
+    }
+    actions
+}
+
 /// Constructs player path based on box path.
 pub fn construct_player_path(
     map: &Map,
@@ -268,6 +768,17 @@ pub fn pushable_boxes(map: &Map) -> HashSet<Vector2<i32>> {
 pub fn reachable_area(
     position: Vector2<i32>,
     can_move: impl Fn(Vector2<i32>) -> bool,
+) -> HashSet<Vector2<i32>> {
+    reachable_area_in::<Square>(position, can_move)
+}
+
+/// Calculates the reachable area starting from a given position on a grid with
+/// the given topology.
+///
+/// See [`reachable_area`] for details.
+pub fn reachable_area_in<T: Topology>(
+    position: Vector2<i32>,
+    can_move: impl Fn(Vector2<i32>) -> bool,
 ) -> HashSet<Vector2<i32>> {
     let mut reachable_area = HashSet::new();
     let mut deque = VecDeque::<Vector2<i32>>::new();
@@ -277,8 +788,8 @@ pub fn reachable_area(
         if !reachable_area.insert(position) {
             continue;
         }
-        for direction in Direction::iter() {
-            let neighbor = position + &direction.into();
+        for &direction in T::directions() {
+            let neighbor = T::neighbor(position, direction);
             if can_move(neighbor) {
                 deque.push_back(neighbor);
             }
@@ -288,14 +799,37 @@ pub fn reachable_area(
     reachable_area
 }
 
+/// Calculates the distances from a given position to all reachable positions.
+///
+/// This function performs a breadth-first search based on the provided
+/// `can_move` function.
+pub(crate) fn distances<T: Topology>(
+    position: Vector2<i32>,
+    can_move: impl Fn(Vector2<i32>) -> bool,
+) -> HashMap<Vector2<i32>, i32> {
+    let mut distances = HashMap::new();
+    let mut deque = VecDeque::new();
+    distances.insert(position, 0);
+    deque.push_back(position);
+
+    while let Some(position) = deque.pop_front() {
+        let distance = distances[&position] + 1;
+        for &direction in T::directions() {
+            let neighbor = T::neighbor(position, direction);
+            if distances.contains_key(&neighbor) || !can_move(neighbor) {
+                continue;
+            }
+            distances.insert(neighbor, distance);
+            deque.push_back(neighbor);
+        }
+    }
+
+    distances
+}
+
 /// Returns the top-left position.
 pub fn normalized_area(area: &HashSet<Vector2<i32>>) -> Option<Vector2<i32>> {
     area.iter()
         .min_by(|a, b| a.y.cmp(&b.y).then_with(|| a.x.cmp(&b.x)))
         .copied()
 }
-
-/// Calculates the Manhattan distance between two 2D vectors.
-fn manhattan_distance(a: Vector2<i32>, b: Vector2<i32>) -> i32 {
-    (a.x - b.x).abs() + (a.y - b.y).abs()
-}
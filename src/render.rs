@@ -0,0 +1,534 @@
+//! Rendering of maps and solutions.
+
+use std::{collections::HashSet, fmt::Write};
+
+use nalgebra::Vector2;
+
+use crate::{
+    actions::Actions,
+    deadlock::calculate_static_deadlocks,
+    direction::Direction,
+    error::VerifyError,
+    map::Map,
+    path_finding::reachable_area,
+    solver::{Solver, Strategy},
+    tiles::Tiles,
+};
+
+/// The colors used to render a map.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Theme {
+    /// The color of the background.
+    pub background: String,
+    /// The color of the walls.
+    pub wall: String,
+    /// The color of the floors.
+    pub floor: String,
+    /// The color of the goals.
+    pub goal: String,
+    /// The color of the boxes which are not on goals.
+    pub box_: String,
+    /// The color of the boxes which are on goals.
+    pub box_on_goal: String,
+    /// The color of the player.
+    pub player: String,
+    /// The color of the overlays.
+    pub overlay: String,
+}
+
+impl Theme {
+    /// Returns a theme with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            background: "#1e1e1e".to_string(),
+            wall: "#5a5a5a".to_string(),
+            floor: "#2d2d2d".to_string(),
+            goal: "#4ec9b0".to_string(),
+            box_: "#ce9178".to_string(),
+            box_on_goal: "#6a9955".to_string(),
+            player: "#569cd6".to_string(),
+            overlay: "#f44747".to_string(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: "#ffffff".to_string(),
+            wall: "#6d6d6d".to_string(),
+            floor: "#e8e8e8".to_string(),
+            goal: "#3cb371".to_string(),
+            box_: "#d2913c".to_string(),
+            box_on_goal: "#8fbc5a".to_string(),
+            player: "#4169e1".to_string(),
+            overlay: "#dc143c".to_string(),
+        }
+    }
+}
+
+/// Additional information drawn on top of a map.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Overlay {
+    /// The static deadlock positions, see [`calculate_static_deadlocks`].
+    StaticDeadlocks,
+    /// The lower bounds of the number of pushes, see [`Solver::lower_bounds`].
+    ///
+    /// In the terminal, lower bounds above 99 are shown as 99 to fit in a
+    /// cell, which is still a lower bound.
+    LowerBounds,
+    /// The area reachable by the player.
+    ReachableArea,
+    /// The tunnels, see [`Solver::tunnels`].
+    Tunnels,
+    /// A path, such as one found by [`find_path`].
+    ///
+    /// [`find_path`]: crate::path_finding::find_path
+    Path(Vec<Vector2<i32>>),
+}
+
+/// The options used to render a map to SVG.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SvgOptions {
+    /// The size of each cell in pixels.
+    pub cell_size: u32,
+    /// The colors.
+    pub theme: Theme,
+    /// The overlays drawn on top of the map.
+    pub overlays: Vec<Overlay>,
+    /// The duration of each frame of an animation in milliseconds.
+    pub frame_duration: u32,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 32,
+            theme: Theme::default(),
+            overlays: Vec::new(),
+            frame_duration: 200,
+        }
+    }
+}
+
+/// Renders the map to SVG.
+pub fn render_svg(map: &Map, options: &SvgOptions) -> String {
+    let mut svg = svg_header(map, options);
+    write_terrain(&mut svg, map, options);
+    write_entities(
+        &mut svg,
+        map,
+        map.player_position(),
+        map.box_positions(),
+        options,
+    );
+    write_overlays(&mut svg, map, options);
+    svg += "</svg>\n";
+    svg
+}
+
+/// Renders each state of the solution to SVG, starting with the initial
+/// state.
+///
+/// Returns an error if the actions are illegal.
+pub fn render_svg_frames(
+    map: &Map,
+    actions: &Actions,
+    options: &SvgOptions,
+) -> Result<Vec<String>, VerifyError> {
+    let mut frames = vec![render_svg(map, options)];
+    let mut frame_map = map.clone();
+    for snapshot in map.replay(actions) {
+        let snapshot = snapshot?;
+        frame_map.set_player_position(snapshot.player_position);
+        if let Some((from, to)) = snapshot.moved_box {
+            frame_map.set_box_position(from, to);
+        }
+        frames.push(render_svg(&frame_map, options));
+    }
+    Ok(frames)
+}
+
+/// Renders the solution to an animated SVG.
+///
+/// Each state is shown for [`SvgOptions::frame_duration`], and the last state
+/// remains visible. Returns an error if the actions are illegal.
+pub fn render_svg_animation(
+    map: &Map,
+    actions: &Actions,
+    options: &SvgOptions,
+) -> Result<String, VerifyError> {
+    let mut states = vec![(map.player_position(), map.box_positions().clone())];
+    for snapshot in map.replay(actions) {
+        let snapshot = snapshot?;
+        states.push((snapshot.player_position, snapshot.box_positions));
+    }
+
+    let mut svg = svg_header(map, options);
+    write_terrain(&mut svg, map, options);
+    let duration = options.frame_duration;
+    for (index, (player_position, box_positions)) in states.iter().enumerate() {
+        let begin = index as u32 * duration;
+        if index + 1 == states.len() {
+            writeln!(
+                svg,
+                r#"<g visibility="hidden"><set attributeName="visibility" to="visible" begin="{begin}ms" fill="freeze"/>"#
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                svg,
+                r#"<g visibility="hidden"><set attributeName="visibility" to="visible" begin="{begin}ms" dur="{duration}ms"/>"#
+            )
+            .unwrap();
+        }
+        write_entities(&mut svg, map, *player_position, box_positions, options);
+        svg += "</g>\n";
+    }
+    write_overlays(&mut svg, map, options);
+    svg += "</svg>\n";
+    Ok(svg)
+}
+
+/// Returns the opening tag and background of the SVG.
+fn svg_header(map: &Map, options: &SvgOptions) -> String {
+    let width = map.dimensions().x as u32 * options.cell_size;
+    let height = map.dimensions().y as u32 * options.cell_size;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+        options.theme.background
+    )
+    .unwrap();
+    svg
+}
+
+/// Writes the walls, floors and goals.
+fn write_terrain(svg: &mut String, map: &Map, options: &SvgOptions) {
+    let size = options.cell_size;
+    for position in positions(map) {
+        let (x, y) = cell_origin(position, size);
+        if map[position].intersects(Tiles::Wall) {
+            writeln!(
+                svg,
+                r#"<rect class="wall" x="{x}" y="{y}" width="{size}" height="{size}" fill="{}"/>"#,
+                options.theme.wall
+            )
+            .unwrap();
+        } else if map[position].intersects(Tiles::Floor) {
+            writeln!(
+                svg,
+                r#"<rect class="floor" x="{x}" y="{y}" width="{size}" height="{size}" fill="{}"/>"#,
+                options.theme.floor
+            )
+            .unwrap();
+        }
+        if map[position].intersects(Tiles::Goal) {
+            writeln!(
+                svg,
+                r#"<circle class="goal" cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                x + size / 2,
+                y + size / 2,
+                size / 6,
+                options.theme.goal
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Writes the boxes and the player.
+fn write_entities(
+    svg: &mut String,
+    map: &Map,
+    player_position: Vector2<i32>,
+    box_positions: &HashSet<Vector2<i32>>,
+    options: &SvgOptions,
+) {
+    let size = options.cell_size;
+    let inset = size / 8;
+    for position in positions(map).filter(|position| box_positions.contains(position)) {
+        let (x, y) = cell_origin(position, size);
+        let color = if map.goal_positions().contains(&position) {
+            &options.theme.box_on_goal
+        } else {
+            &options.theme.box_
+        };
+        writeln!(
+            svg,
+            r#"<rect class="box" x="{}" y="{}" width="{}" height="{}" rx="{}" fill="{color}"/>"#,
+            x + inset,
+            y + inset,
+            size - 2 * inset,
+            size - 2 * inset,
+            inset
+        )
+        .unwrap();
+    }
+    let (x, y) = cell_origin(player_position, size);
+    writeln!(
+        svg,
+        r#"<circle class="player" cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+        x + size / 2,
+        y + size / 2,
+        size / 2 - inset,
+        options.theme.player
+    )
+    .unwrap();
+}
+
+/// Writes the overlays.
+fn write_overlays(svg: &mut String, map: &Map, options: &SvgOptions) {
+    let size = options.cell_size;
+    let color = &options.theme.overlay;
+    for overlay in &options.overlays {
+        match overlay {
+            Overlay::StaticDeadlocks => {
+                let dead_positions = calculate_static_deadlocks(map);
+                for position in positions(map).filter(|position| dead_positions.contains(position))
+                {
+                    let (x, y) = cell_origin(position, size);
+                    let (inset, end) = (size / 4, size - size / 4);
+                    writeln!(
+                        svg,
+                        r#"<path class="deadlock" d="M{} {}L{} {}M{} {}L{} {}" stroke="{color}" stroke-width="2"/>"#,
+                        x + inset,
+                        y + inset,
+                        x + end,
+                        y + end,
+                        x + end,
+                        y + inset,
+                        x + inset,
+                        y + end
+                    )
+                    .unwrap();
+                }
+            }
+            Overlay::LowerBounds => {
+                let solver = Solver::new(map.clone(), Strategy::Fast);
+                for position in positions(map) {
+                    let Some(lower_bound) = solver.lower_bounds().get(&position) else {
+                        continue;
+                    };
+                    let (x, y) = cell_origin(position, size);
+                    writeln!(
+                        svg,
+                        r#"<text class="lower-bound" x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="{color}">{lower_bound}</text>"#,
+                        x + size / 2,
+                        y + size / 2,
+                        size / 3
+                    )
+                    .unwrap();
+                }
+            }
+            Overlay::ReachableArea => {
+                let area = reachable_area(map.player_position(), |position| map.can_move(position));
+                for position in positions(map).filter(|position| area.contains(position)) {
+                    let (x, y) = cell_origin(position, size);
+                    writeln!(
+                        svg,
+                        r#"<rect class="reachable" x="{x}" y="{y}" width="{size}" height="{size}" fill="{color}" fill-opacity="0.2"/>"#
+                    )
+                    .unwrap();
+                }
+            }
+            Overlay::Tunnels => {
+                let solver = Solver::new(map.clone(), Strategy::Fast);
+                let mut tunnels: Vec<_> = solver.tunnels().iter().copied().collect();
+                tunnels.sort_by_key(|(position, direction)| {
+                    (position.y, position.x, *direction as u8)
+                });
+                for (position, direction) in tunnels {
+                    let (x, y) = cell_origin(position, size);
+                    let center = Vector2::new((x + size / 2) as i32, (y + size / 2) as i32);
+                    let end = center + Vector2::<i32>::from(direction) * (size / 2) as i32;
+                    writeln!(
+                        svg,
+                        r#"<line class="tunnel" x1="{}" y1="{}" x2="{}" y2="{}" stroke="{color}" stroke-width="2"/>"#,
+                        center.x, center.y, end.x, end.y
+                    )
+                    .unwrap();
+                }
+            }
+            Overlay::Path(path) => {
+                let points: Vec<_> = path
+                    .iter()
+                    .map(|&position| {
+                        let (x, y) = cell_origin(position, size);
+                        format!("{},{}", x + size / 2, y + size / 2)
+                    })
+                    .collect();
+                writeln!(
+                    svg,
+                    r#"<polyline class="path" points="{}" fill="none" stroke="{color}" stroke-width="{}"/>"#,
+                    points.join(" "),
+                    size / 8
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// The options used to render a map to a terminal.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TerminalOptions {
+    /// Whether to use ANSI colors.
+    pub colors: bool,
+    /// The overlays drawn on top of the map.
+    pub overlays: Vec<Overlay>,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            colors: true,
+            overlays: Vec::new(),
+        }
+    }
+}
+
+/// A cell rendered to a terminal.
+struct TerminalCell {
+    glyph: String,
+    foreground: Option<u8>,
+    background: Option<u8>,
+}
+
+/// Renders the map to a string for terminals, using Unicode glyphs and
+/// optionally ANSI colors.
+///
+/// Each cell is two columns wide, so that the cells are roughly square.
+pub fn render_terminal(map: &Map, options: &TerminalOptions) -> String {
+    let mut cells: Vec<_> = positions(map)
+        .map(|position| {
+            let tiles = map[position];
+            let (glyph, foreground) = if tiles.intersects(Tiles::Wall) {
+                ("██", 244)
+            } else if tiles.contains(Tiles::Box | Tiles::Goal) {
+                ("▣ ", 34)
+            } else if tiles.intersects(Tiles::Box) {
+                ("■ ", 178)
+            } else if tiles.contains(Tiles::Player | Tiles::Goal) {
+                ("◉ ", 33)
+            } else if tiles.intersects(Tiles::Player) {
+                ("● ", 33)
+            } else if tiles.intersects(Tiles::Goal) {
+                ("· ", 160)
+            } else {
+                return TerminalCell {
+                    glyph: "  ".to_string(),
+                    foreground: None,
+                    background: None,
+                };
+            };
+            TerminalCell {
+                glyph: glyph.to_string(),
+                foreground: Some(foreground),
+                background: None,
+            }
+        })
+        .collect();
+
+    let dimensions = map.dimensions();
+    let index = |position: Vector2<i32>| (position.y * dimensions.x + position.x) as usize;
+    // Overlays only replace the glyphs of cells without boxes or the player
+    let is_free = |position: Vector2<i32>| {
+        map.in_bounds(position)
+            && !map[position].intersects(Tiles::Wall | Tiles::Box | Tiles::Player)
+    };
+    for overlay in &options.overlays {
+        match overlay {
+            Overlay::StaticDeadlocks => {
+                for position in calculate_static_deadlocks(map) {
+                    if is_free(position) {
+                        let cell = &mut cells[index(position)];
+                        cell.glyph = "✕ ".to_string();
+                        cell.foreground = Some(160);
+                    }
+                }
+            }
+            Overlay::LowerBounds => {
+                let solver = Solver::new(map.clone(), Strategy::Fast);
+                for (&position, lower_bound) in solver.lower_bounds() {
+                    if is_free(position) {
+                        let cell = &mut cells[index(position)];
+                        // Cells are two columns wide
+                        cell.glyph = format!("{:>2}", lower_bound.min(&99));
+                        cell.foreground = Some(245);
+                    }
+                }
+            }
+            Overlay::ReachableArea => {
+                let area = reachable_area(map.player_position(), |position| map.can_move(position));
+                for position in area {
+                    cells[index(position)].background = Some(237);
+                }
+            }
+            Overlay::Tunnels => {
+                let solver = Solver::new(map.clone(), Strategy::Fast);
+                for &(position, direction) in solver.tunnels() {
+                    if is_free(position) {
+                        let cell = &mut cells[index(position)];
+                        cell.glyph = match direction {
+                            Direction::Up => "↑ ",
+                            Direction::Right => "→ ",
+                            Direction::Down => "↓ ",
+                            Direction::Left => "← ",
+                        }
+                        .to_string();
+                        cell.foreground = Some(245);
+                    }
+                }
+            }
+            Overlay::Path(path) => {
+                for &position in path {
+                    if !map.in_bounds(position) {
+                        continue;
+                    }
+                    let cell = &mut cells[index(position)];
+                    if is_free(position) {
+                        cell.glyph = "∘ ".to_string();
+                    }
+                    cell.background = Some(24);
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for row in cells.chunks(dimensions.x as usize) {
+        for cell in row {
+            if !options.colors {
+                output += &cell.glyph;
+                continue;
+            }
+            if let Some(foreground) = cell.foreground {
+                write!(output, "\x1b[38;5;{foreground}m").unwrap();
+            }
+            if let Some(background) = cell.background {
+                write!(output, "\x1b[48;5;{background}m").unwrap();
+            }
+            write!(output, "{}\x1b[0m", cell.glyph).unwrap();
+        }
+        output += "\n";
+    }
+    output
+}
+
+/// Returns the positions of the map in row-major order.
+fn positions(map: &Map) -> impl Iterator<Item = Vector2<i32>> {
+    let dimensions = map.dimensions();
+    (0..dimensions.y).flat_map(move |y| (0..dimensions.x).map(move |x| Vector2::new(x, y)))
+}
+
+/// Returns the coordinates of the top-left corner of the cell.
+fn cell_origin(position: Vector2<i32>, cell_size: u32) -> (u32, u32) {
+    (position.x as u32 * cell_size, position.y as u32 * cell_size)
+}
@@ -0,0 +1,146 @@
+//! Replay of actions on a map.
+
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Enumerate,
+    slice,
+};
+
+use nalgebra::Vector2;
+
+use crate::{
+    action::Action, actions::Actions, error::VerifyError, map::Map, rules::MovementRules,
+    tiles::Tiles,
+};
+
+/// A snapshot of the state after an action.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Snapshot {
+    /// The position of the player.
+    pub player_position: Vector2<i32>,
+    /// The positions the box was moved from and to, or `None` if no box was
+    /// moved.
+    pub moved_box: Option<(Vector2<i32>, Vector2<i32>)>,
+    /// The positions of the boxes.
+    pub box_positions: HashSet<Vector2<i32>>,
+}
+
+/// An iterator that replays actions on a map.
+///
+/// This struct is created by [`Map::replay`]. It yields a snapshot after each
+/// action, or an error and then stops if an action is illegal.
+#[derive(Clone, Debug)]
+pub struct Replay<'a> {
+    map: &'a Map,
+    actions: Enumerate<slice::Iter<'a, Action>>,
+    player_position: Vector2<i32>,
+    box_positions: HashSet<Vector2<i32>>,
+    box_ids: HashMap<Vector2<i32>, u32>,
+    movement_rules: MovementRules,
+    failed: bool,
+}
+
+impl<'a> Replay<'a> {
+    /// Creates a new `Replay`.
+    pub(crate) fn new(map: &'a Map, actions: &'a Actions, movement_rules: MovementRules) -> Self {
+        Self {
+            map,
+            actions: actions.iter().enumerate(),
+            player_position: map.player_position(),
+            box_positions: map.box_positions().clone(),
+            box_ids: map
+                .box_positions()
+                .iter()
+                .map(|&position| (position, map.box_id(position)))
+                .collect(),
+            movement_rules,
+            failed: false,
+        }
+    }
+
+    /// Returns the current position of the player.
+    pub fn player_position(&self) -> Vector2<i32> {
+        self.player_position
+    }
+
+    /// Returns a reference to the current positions of the boxes.
+    pub fn box_positions(&self) -> &HashSet<Vector2<i32>> {
+        &self.box_positions
+    }
+
+    /// Returns `true` if every box is on a goal with the same ID.
+    pub(crate) fn is_solved(&self) -> bool {
+        self.box_positions == *self.map.goal_positions()
+            && self
+                .box_ids
+                .iter()
+                .all(|(&position, &id)| self.map.goal_id(position) == id)
+    }
+
+    /// Performs the action and returns the snapshot after it.
+    fn step(&mut self, index: usize, action: Action) -> Result<Snapshot, VerifyError> {
+        if action.is_push() && !self.movement_rules.can_push() {
+            return Err(VerifyError::PushNotAllowed(index));
+        }
+        if action.is_pull() && !self.movement_rules.can_pull() {
+            return Err(VerifyError::PullNotAllowed(index));
+        }
+        let direction = action.direction();
+        let new_player_position = self.player_position + &direction.into();
+        if !self.map.in_bounds(new_player_position)
+            || self.map[new_player_position].intersects(Tiles::Wall)
+        {
+            return Err(VerifyError::MoveBlocked(index));
+        }
+        let mut moved_box = None;
+        if self.box_positions.contains(&new_player_position) {
+            match action {
+                Action::Move(_) => return Err(VerifyError::PushDeclaredAsMove(index)),
+                Action::Pull(_) => return Err(VerifyError::MoveBlocked(index)),
+                Action::Push(_) => {}
+            }
+            let new_box_position = new_player_position + &direction.into();
+            if !self.map.in_bounds(new_box_position)
+                || self.map[new_box_position].intersects(Tiles::Wall)
+                || self.box_positions.contains(&new_box_position)
+            {
+                return Err(VerifyError::PushBlocked(index));
+            }
+            moved_box = Some((new_player_position, new_box_position));
+        } else if action.is_push() {
+            return Err(VerifyError::MoveDeclaredAsPush(index));
+        } else if action.is_pull() {
+            let box_position = self.player_position - &direction.into();
+            if !self.box_positions.contains(&box_position) {
+                return Err(VerifyError::MoveDeclaredAsPull(index));
+            }
+            moved_box = Some((box_position, self.player_position));
+        }
+        if let Some((from, to)) = moved_box {
+            self.box_positions.remove(&from);
+            self.box_positions.insert(to);
+            let id = self.box_ids.remove(&from).unwrap();
+            self.box_ids.insert(to, id);
+        }
+        self.player_position = new_player_position;
+        Ok(Snapshot {
+            player_position: self.player_position,
+            moved_box,
+            box_positions: self.box_positions.clone(),
+        })
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = Result<Snapshot, VerifyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let (index, action) = self.actions.next()?;
+        let snapshot = self.step(index, *action);
+        self.failed = snapshot.is_err();
+        Some(snapshot)
+    }
+}
@@ -0,0 +1,185 @@
+//! A level played in reverse mode.
+
+use std::collections::HashSet;
+
+use nalgebra::Vector2;
+
+use crate::{
+    action::Action,
+    actions::Actions,
+    direction::Direction,
+    error::ActionError,
+    level::Level,
+    map::Map,
+    path_finding::{find_path, reachable_area},
+    rules::MovementRules,
+    tiles::Tiles,
+};
+
+/// A level played in reverse mode.
+///
+/// In reverse mode, the boxes start on the goals and the player pulls them back
+/// to their initial positions. Before the first pull, the player can jump to
+/// any floor which is not occupied by a box.
+///
+/// The moves and pulls are performed by a [`Level`] with
+/// [`MovementRules::PullOnly`], and recorded as [`Action::Move`] and
+/// [`Action::Pull`]. This type only adds what is specific to reverse mode:
+/// the initial placement of the boxes, jumps and the conversion to a forward
+/// solution.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ReverseLevel {
+    level: Level,
+    forward_map: Map,
+    /// The levels before each jump.
+    jumps: Vec<Level>,
+}
+
+impl ReverseLevel {
+    /// Creates a new `ReverseLevel` from the map of a level.
+    ///
+    /// The boxes are placed on the goals. The player stays at its initial
+    /// position, unless the position is occupied by a box, in which case the
+    /// player is placed on the top-left free floor.
+    ///
+    /// Returns [`ActionError::NoFreeFloor`] if there is no free floor for the
+    /// player.
+    pub fn from_map(forward_map: Map) -> Result<Self, ActionError> {
+        let mut map = forward_map.clone();
+        let box_positions: Vec<_> = map
+            .box_positions()
+            .difference(map.goal_positions())
+            .copied()
+            .collect();
+        let goal_positions: Vec<_> = map
+            .goal_positions()
+            .difference(map.box_positions())
+            .copied()
+            .collect();
+        for (box_position, goal_position) in box_positions.into_iter().zip(goal_positions) {
+            map.set_box_position(box_position, goal_position);
+        }
+
+        if map.box_positions().contains(&map.player_position()) {
+            let player_position = (0..map.dimensions().y)
+                .flat_map(|y| (0..map.dimensions().x).map(move |x| Vector2::new(x, y)))
+                .find(|&position| map[position] == Tiles::Floor)
+                .ok_or(ActionError::NoFreeFloor)?;
+            map.set_player_position(player_position);
+        }
+
+        Ok(Self {
+            level: Self::pull_only_level(map),
+            forward_map,
+            jumps: Vec::new(),
+        })
+    }
+
+    /// Returns a reference to the map of the level.
+    pub fn map(&self) -> &Map {
+        self.level.map()
+    }
+
+    /// Returns a reference to the actions of the level.
+    pub fn actions(&self) -> &Actions {
+        self.level.actions()
+    }
+
+    /// Moves the player in the specified direction without pulling a box.
+    pub fn do_move(&mut self, direction: Direction) -> Result<(), ActionError> {
+        self.level.do_action(direction).map(|_| ())
+    }
+
+    /// Moves the player in the specified direction and pulls the box behind
+    /// the player.
+    pub fn do_pull(&mut self, direction: Direction) -> Result<(), ActionError> {
+        self.level.do_pull(direction).map(|_| ())
+    }
+
+    /// Moves the player to the specified position.
+    ///
+    /// Jumps are only allowed before the first pull. Since the moves before
+    /// the first pull are irrelevant to the solution, they are discarded. The
+    /// jump can be undone like an action.
+    pub fn jump(&mut self, position: Vector2<i32>) -> Result<(), ActionError> {
+        if self.actions().pulls() != 0 {
+            return Err(ActionError::JumpNotAllowed);
+        }
+        if !self.map().can_move(position) || !self.map()[position].intersects(Tiles::Floor) {
+            return Err(ActionError::MoveBlocked);
+        }
+        let mut map = self.map().clone();
+        map.set_player_position(position);
+        let level = std::mem::replace(&mut self.level, Self::pull_only_level(map));
+        self.jumps.push(level);
+        Ok(())
+    }
+
+    /// Undoes the last action or jump.
+    pub fn undo_action(&mut self) -> Result<(), ActionError> {
+        if self.level.undo_action().is_ok() {
+            return Ok(());
+        }
+        self.level = self.jumps.pop().ok_or(ActionError::NoActions)?;
+        Ok(())
+    }
+
+    /// Returns `true` if the level is solved.
+    ///
+    /// The level is solved when the boxes are at their initial positions and
+    /// the player can reach its initial position.
+    pub fn is_solved(&self) -> bool {
+        self.map().box_positions() == self.forward_map.box_positions()
+            && self
+                .player_reachable_area()
+                .contains(&self.forward_map.player_position())
+    }
+
+    /// Returns the reachable area for the player.
+    pub fn player_reachable_area(&self) -> HashSet<Vector2<i32>> {
+        reachable_area(self.map().player_position(), |position| {
+            self.map().can_move(position)
+        })
+    }
+
+    /// Converts the actions of the solved level into a forward solution.
+    ///
+    /// Returns `None` if the level is not solved.
+    pub fn forward_actions(&self) -> Option<Actions> {
+        if !self.is_solved() {
+            return None;
+        }
+        let mut forward_actions = Actions::new();
+        let path = find_path(
+            self.forward_map.player_position(),
+            self.map().player_position(),
+            |position| self.map().can_move(position),
+        )?;
+        forward_actions.extend(
+            path.windows(2)
+                .map(|positions| Direction::try_from(positions[1] - positions[0]).unwrap())
+                .map(Action::Move),
+        );
+        // A pull in reverse mode is a push in the opposite direction
+        forward_actions.extend(self.actions().iter().rev().map(|action| match *action {
+            Action::Move(direction) => Action::Move(-direction),
+            Action::Push(direction) => Action::Pull(-direction),
+            Action::Pull(direction) => Action::Push(-direction),
+        }));
+
+        // Discard the moves after the last push
+        let len = forward_actions
+            .iter()
+            .rposition(|action| action.is_push())
+            .map_or(0, |index| index + 1);
+        forward_actions.truncate(len);
+        Some(forward_actions)
+    }
+
+    /// Creates a level where the player can only move and pull boxes.
+    fn pull_only_level(map: Map) -> Level {
+        let mut level = Level::from_map(map);
+        level.set_movement_rules(MovementRules::PullOnly);
+        level
+    }
+}
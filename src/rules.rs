@@ -0,0 +1,25 @@
+//! Rules of box movement.
+
+/// The rules which determine how the player can move boxes.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub enum MovementRules {
+    /// Boxes can only be pushed, as in standard Sokoban.
+    #[default]
+    PushOnly,
+    /// Boxes can only be pulled.
+    PullOnly,
+    /// Boxes can be both pushed and pulled.
+    PushAndPull,
+}
+
+impl MovementRules {
+    /// Checks if boxes can be pushed.
+    pub fn can_push(self) -> bool {
+        matches!(self, Self::PushOnly | Self::PushAndPull)
+    }
+
+    /// Checks if boxes can be pulled.
+    pub fn can_pull(self) -> bool {
+        matches!(self, Self::PullOnly | Self::PushAndPull)
+    }
+}
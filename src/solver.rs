@@ -1,8 +1,11 @@
 //! A solver for the Sokoban problem.
 
 use std::{
-    cell::OnceCell,
+    cell::{Cell, OnceCell},
+    cmp::Reverse,
     collections::{BinaryHeap, HashMap, HashSet},
+    hash::Hash,
+    time::{Duration, Instant},
 };
 
 use itertools::Itertools;
@@ -11,8 +14,12 @@ use nalgebra::Vector2;
 use crate::{
     direction::Direction,
     node::Node,
-    path_finding::{find_path, reachable_area},
+    path_finding::{
+        box_move_distances, find_path, find_path_in, normalized_area, reachable_area_in,
+    },
+    rules::MovementRules,
     state::State,
+    topology::{Grid, Square, Topology},
     Action, Actions, Map, SearchError, Tiles,
 };
 
@@ -35,8 +42,15 @@ pub enum Strategy {
 pub struct Solver {
     map: Map,
     strategy: Strategy,
+    movement_rules: MovementRules,
     lower_bounds: OnceCell<HashMap<Vector2<i32>, i32>>,
+    id_lower_bounds: OnceCell<HashMap<u32, HashMap<Vector2<i32>, i32>>>,
     tunnels: OnceCell<HashSet<(Vector2<i32>, Direction)>>,
+    node_limit: Option<usize>,
+    time_limit: Option<Duration>,
+    start_time: Cell<Option<Instant>>,
+    expanded_nodes: Cell<usize>,
+    generated_nodes: Cell<usize>,
 }
 
 impl Solver {
@@ -45,28 +59,78 @@ impl Solver {
         Self {
             map,
             strategy,
+            movement_rules: MovementRules::default(),
             lower_bounds: OnceCell::new(),
+            id_lower_bounds: OnceCell::new(),
             tunnels: OnceCell::new(),
+            node_limit: None,
+            time_limit: None,
+            start_time: Cell::new(None),
+            expanded_nodes: Cell::new(0),
+            generated_nodes: Cell::new(0),
         }
     }
 
+    /// Sets the maximum number of nodes expanded by [`a_star_search`].
+    ///
+    /// The search returns [`SearchError::LimitExceeded`] once the limit is
+    /// reached.
+    ///
+    /// [`a_star_search`]: Solver::a_star_search
+    pub fn set_node_limit(&mut self, node_limit: Option<usize>) {
+        self.node_limit = node_limit;
+    }
+
+    /// Sets the maximum duration of [`a_star_search`].
+    ///
+    /// The search returns [`SearchError::LimitExceeded`] once the limit is
+    /// reached.
+    ///
+    /// [`a_star_search`]: Solver::a_star_search
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.time_limit = time_limit;
+    }
+
+    /// Sets the movement rules of the search.
+    ///
+    /// When pulls are allowed, the solution may contain [`Action::Pull`], and
+    /// pushes and pulls are both counted as pushes by [`Strategy::OptimalPush`].
+    pub fn set_movement_rules(&mut self, movement_rules: MovementRules) {
+        self.movement_rules = movement_rules;
+        self.lower_bounds = OnceCell::new();
+        self.id_lower_bounds = OnceCell::new();
+        self.tunnels = OnceCell::new();
+    }
+
+    /// Returns the movement rules of the search.
+    pub fn movement_rules(&self) -> MovementRules {
+        self.movement_rules
+    }
+
     /// Searches for solution using the A* algorithm.
     pub fn a_star_search(&self) -> Result<Actions, SearchError> {
         let mut heap = BinaryHeap::new();
         let mut came_from = HashMap::new();
         let mut visited = HashSet::new();
 
-        let state: State = self.map.clone().into();
+        self.expanded_nodes.set(0);
+        self.generated_nodes.set(0);
+        self.start_time.set(Some(Instant::now()));
+
+        let state = self.initial_state()?;
+        visited.insert(state.normalized_hash(&self.map));
         heap.push(Node::new(state, 0, 0, self));
 
         while let Some(node) = heap.pop() {
             if node.state.is_solved(self) {
                 return Ok(self.construct_actions(node.state, &came_from));
             }
+            self.expand()?;
             for successor in node.successors(self) {
                 if !visited.insert(successor.state.normalized_hash(&self.map)) {
                     continue;
                 }
+                self.generated_nodes.set(self.generated_nodes.get() + 1);
                 came_from.insert(successor.state.clone(), node.state.clone());
                 heap.push(successor);
             }
@@ -76,7 +140,7 @@ impl Solver {
 
     /// Searches for solution using the IDA* algorithm.
     pub fn ida_star_search(&self) -> Result<(), SearchError> {
-        let state: State = self.map.clone().into();
+        let state = self.initial_state()?;
         let mut threshold = state.heuristic(self);
         let node = Node::new(state, 0, 0, self);
         loop {
@@ -125,17 +189,61 @@ impl Solver {
         self.strategy
     }
 
+    /// Returns the number of nodes expanded by the last [`a_star_search`].
+    ///
+    /// [`a_star_search`]: Solver::a_star_search
+    pub fn expanded_nodes(&self) -> usize {
+        self.expanded_nodes.get()
+    }
+
+    /// Returns the number of nodes generated by the last [`a_star_search`].
+    ///
+    /// [`a_star_search`]: Solver::a_star_search
+    pub fn generated_nodes(&self) -> usize {
+        self.generated_nodes.get()
+    }
+
     /// Returns a reference to the set of lower bounds.
     pub fn lower_bounds(&self) -> &HashMap<Vector2<i32>, i32> {
         // FIXME: Calculate lower bounds based on strategy
         self.lower_bounds.get_or_init(|| {
             assert!(self.strategy == Strategy::OptimalPush || self.strategy == Strategy::Fast);
-            let mut lower_bounds = self.calculate_minimum_push();
+            let mut lower_bounds = self.calculate_minimum_push(self.map.goal_positions());
             lower_bounds.shrink_to_fit();
             lower_bounds
         })
     }
 
+    /// Returns the lower bound of a box with the ID at the position, or `None`
+    /// if the box can not be pushed to a goal with the same ID.
+    pub(crate) fn lower_bound(&self, position: Vector2<i32>, id: u32) -> Option<i32> {
+        if !self.map.has_ids() {
+            return self.lower_bounds().get(&position).copied();
+        }
+        let id_lower_bounds = self.id_lower_bounds.get_or_init(|| {
+            let goal_ids: HashSet<_> = self
+                .map
+                .goal_positions()
+                .iter()
+                .map(|&position| self.map.goal_id(position))
+                .collect();
+            goal_ids
+                .into_iter()
+                .map(|id| {
+                    let goal_positions = self
+                        .map
+                        .goal_positions()
+                        .iter()
+                        .copied()
+                        .filter(|&position| self.map.goal_id(position) == id)
+                        .collect();
+                    (id, self.calculate_minimum_push(&goal_positions))
+                })
+                .collect()
+        });
+        id_lower_bounds.get(&id)?.get(&position).copied()
+    }
+
     /// Returns a reference to the set of tunnels.
     pub fn tunnels(&self) -> &HashSet<(Vector2<i32>, Direction)> {
         self.tunnels.get_or_init(|| {
@@ -146,75 +254,18 @@ impl Solver {
     }
 
     /// Calculates and returns the minimum number of pushes to push the box to
-    /// the nearest goal.
-    fn calculate_minimum_push(&self) -> HashMap<Vector2<i32>, i32> {
-        let mut lower_bounds = HashMap::new();
-        for goal_position in self.map.goal_positions() {
-            lower_bounds.insert(*goal_position, 0);
-
-            for pull_direction in Direction::iter() {
-                let new_box_position = goal_position + &pull_direction.into();
-                let new_player_position = new_box_position + &pull_direction.into();
-                if !self.map.in_bounds(new_player_position)
-                    || self.map[new_box_position].intersects(Tiles::Wall)
-                    || self.map[new_player_position].intersects(Tiles::Wall)
-                {
-                    continue;
-                }
-                self.calculate_minimum_push_to(
-                    *goal_position,
-                    new_player_position,
-                    &mut lower_bounds,
-                    &mut HashSet::new(),
-                );
-                break;
-            }
-        }
-        lower_bounds
-    }
-
-    /// Calculates the minimum push of the box to the specified position.
+    /// the nearest of the goals.
     ///
-    /// Place the box on the goal, then calculate all the positions the box can
-    /// be pulled to and the minimum pulls it can be pulled to that position.
-    fn calculate_minimum_push_to(
+    /// When pulls are allowed, pushes and pulls are counted as box moves.
+    fn calculate_minimum_push(
         &self,
-        box_position: Vector2<i32>,
-        player_position: Vector2<i32>,
-        lower_bounds: &mut HashMap<Vector2<i32>, i32>,
-        visited: &mut HashSet<(Vector2<i32>, Direction)>,
-    ) {
-        let player_reachable_area = reachable_area(player_position, |position| {
-            !(self.map[position].intersects(Tiles::Wall) || position == box_position)
-        });
-        for pull_direction in Direction::iter() {
-            let new_box_position = box_position + &pull_direction.into();
-            if self.map[new_box_position].intersects(Tiles::Wall) {
-                continue;
-            }
-            let new_player_position = new_box_position + &pull_direction.into();
-            if self.map[new_player_position].intersects(Tiles::Wall)
-                || !player_reachable_area.contains(&new_player_position)
-            {
-                continue;
-            }
-
-            let lower_bound = *lower_bounds.get(&new_box_position).unwrap_or(&i32::MAX);
-            if !visited.insert((new_box_position, pull_direction)) {
-                continue;
-            }
-            let new_lower_bound = lower_bounds[&box_position] + 1;
-            if new_lower_bound < lower_bound {
-                lower_bounds.insert(new_box_position, new_lower_bound);
-            }
-
-            self.calculate_minimum_push_to(
-                new_box_position,
-                new_player_position,
-                lower_bounds,
-                visited,
-            );
-        }
+        goal_positions: &HashSet<Vector2<i32>>,
+    ) -> HashMap<Vector2<i32>, i32> {
+        box_move_distances::<Square>(
+            goal_positions.iter().copied(),
+            self.movement_rules,
+            |position| self.map.in_bounds(position) && self.map[position].intersects(Tiles::Floor),
+        )
     }
 
     /// Calculates and returns the set of tunnels.
@@ -224,6 +275,10 @@ impl Solver {
     /// pre-calculated.
     fn calculate_tunnels(&self) -> HashSet<(Vector2<i32>, Direction)> {
         let mut tunnels = HashSet::new();
+        // Pushes into tunnels may be undone by pulls
+        if self.movement_rules != MovementRules::PushOnly {
+            return tunnels;
+        }
         for x in 1..self.map.dimensions().x - 1 {
             for y in 1..self.map.dimensions().y - 1 {
                 let box_position = Vector2::new(x, y);
@@ -262,6 +317,40 @@ impl Solver {
         tunnels
     }
 
+    /// Returns the initial state of the search.
+    ///
+    /// Returns [`SearchError::NoSolution`] if a box can not be pushed to any
+    /// goal with the same ID.
+    fn initial_state(&self) -> Result<State, SearchError> {
+        let state: State = self.map.clone().into();
+        if state
+            .box_positions
+            .iter()
+            .any(|&position| self.lower_bound(position, state.box_id(position)).is_none())
+        {
+            return Err(SearchError::NoSolution);
+        }
+        Ok(state)
+    }
+
+    /// Counts an expanded node, or returns an error if the node limit or the
+    /// time limit is reached.
+    fn expand(&self) -> Result<(), SearchError> {
+        if self
+            .node_limit
+            .is_some_and(|node_limit| self.expanded_nodes.get() >= node_limit)
+            || self.time_limit.is_some_and(|time_limit| {
+                self.start_time
+                    .get()
+                    .is_some_and(|start_time| start_time.elapsed() >= time_limit)
+            })
+        {
+            return Err(SearchError::LimitExceeded);
+        }
+        self.expanded_nodes.set(self.expanded_nodes.get() + 1);
+        Ok(())
+    }
+
     fn construct_actions(&self, mut state: State, came_from: &HashMap<State, State>) -> Actions {
         let mut actions = Actions::new();
         while let Some(prev_state) = came_from.get(&state) {
@@ -282,10 +371,17 @@ impl Solver {
             let push_direction =
                 Direction::try_from(Vector2::new(diff.x.signum(), diff.y.signum())).unwrap();
 
+            // A pull leaves the player in front of the box
+            let is_pull = state.player_position == box_position + &push_direction.into();
+
             // Find the path for the player to reach the box position before pushing it
             let mut new_actions: Vec<_> = find_path(
                 prev_state.player_position,
-                previous_box_position - &push_direction.into(),
+                if is_pull {
+                    box_position
+                } else {
+                    previous_box_position - &push_direction.into()
+                },
                 |position| {
                     !self.map()[position].intersects(Tiles::Wall)
                         && !prev_state.box_positions.contains(&position)
@@ -297,6 +393,12 @@ impl Solver {
             .map(Action::Move)
             .collect();
 
+            if is_pull {
+                new_actions.push(Action::Pull(push_direction));
+                actions.splice(0..0, new_actions.iter().copied());
+                state = prev_state.clone();
+                continue;
+            }
             new_actions.push(Action::Push(push_direction));
 
             let mut new_box_position = previous_box_position + &push_direction.into();
@@ -311,3 +413,254 @@ impl Solver {
         actions
     }
 }
+
+/// A search problem where each step costs one, such as pushes of
+/// [`GridSolver`] or moves of [`MultiSolver`].
+///
+/// [`MultiSolver`]: crate::multiban::MultiSolver
+pub(crate) trait SearchProblem {
+    /// A state of the search.
+    type State;
+    /// The key of a state, states with the same key are expanded only once.
+    type Key: Eq + Hash;
+    /// A step from a state to one of its successors.
+    type Step: Copy;
+
+    /// Returns the key of the state.
+    fn key(&self, state: &Self::State) -> Self::Key;
+
+    /// Returns a lower bound of the number of steps from the state to a goal
+    /// state, or `None` if no goal state is reachable.
+    fn heuristic(&self, state: &Self::State) -> Option<i32>;
+
+    /// Checks if the state is a goal state.
+    fn is_goal(&self, state: &Self::State) -> bool;
+
+    /// Returns the successors of the state along with the steps to them.
+    fn successors(&self, state: &Self::State) -> Vec<(Self::Step, Self::State)>;
+}
+
+/// A node of the search tree of [`a_star_search`].
+struct SearchNode<S, T> {
+    state: S,
+    parent: Option<(usize, T)>,
+}
+
+/// Searches for the shortest sequence of steps from the state to a goal state
+/// using the A* algorithm.
+///
+/// `expanded_nodes` is reset and counts the expanded nodes, and the search
+/// returns [`SearchError::LimitExceeded`] once it reaches `node_limit`.
+pub(crate) fn a_star_search<P: SearchProblem>(
+    problem: &P,
+    state: P::State,
+    node_limit: Option<usize>,
+    expanded_nodes: &Cell<usize>,
+) -> Result<Vec<P::Step>, SearchError> {
+    expanded_nodes.set(0);
+
+    let heuristic = problem.heuristic(&state).ok_or(SearchError::NoSolution)?;
+    let mut nodes = vec![SearchNode {
+        state,
+        parent: None,
+    }];
+    let mut heap = BinaryHeap::from([Reverse((heuristic, 0, 0))]);
+    let mut visited = HashSet::new();
+    while let Some(Reverse((_, steps, index))) = heap.pop() {
+        if problem.is_goal(&nodes[index].state) {
+            let mut steps = Vec::new();
+            let mut index = index;
+            while let Some((parent, step)) = nodes[index].parent {
+                steps.push(step);
+                index = parent;
+            }
+            steps.reverse();
+            return Ok(steps);
+        }
+        if !visited.insert(problem.key(&nodes[index].state)) {
+            continue;
+        }
+        if node_limit.is_some_and(|node_limit| expanded_nodes.get() >= node_limit) {
+            return Err(SearchError::LimitExceeded);
+        }
+        expanded_nodes.set(expanded_nodes.get() + 1);
+
+        for (step, state) in problem.successors(&nodes[index].state) {
+            let Some(heuristic) = problem.heuristic(&state) else {
+                continue;
+            };
+            nodes.push(SearchNode {
+                state,
+                parent: Some((index, step)),
+            });
+            heap.push(Reverse((steps + 1 + heuristic, steps + 1, nodes.len() - 1)));
+        }
+    }
+    Err(SearchError::NoSolution)
+}
+
+/// A state of the search of [`GridSolver`].
+pub(crate) struct GridState {
+    /// The positions of the boxes, sorted in reading order.
+    box_positions: Vec<Vector2<i32>>,
+    player_position: Vector2<i32>,
+}
+
+/// A push optimal solver for maps on any grid topology, including square
+/// [`Map`]s and Hexoban and Trioban maps.
+///
+/// Unlike [`Solver`], Sokoban+ IDs and movement rules are not supported.
+#[derive(Clone, Debug)]
+pub struct GridSolver<M: Grid> {
+    map: M,
+    lower_bounds: HashMap<Vector2<i32>, i32>,
+    node_limit: Option<usize>,
+    expanded_nodes: Cell<usize>,
+}
+
+impl<M: Grid> GridSolver<M> {
+    /// Creates a new `GridSolver`.
+    pub fn new(map: M) -> Self {
+        Self {
+            lower_bounds: box_move_distances::<M::Topology>(
+                map.goal_positions().iter().copied(),
+                MovementRules::PushOnly,
+                |position| map.is_floor(position),
+            ),
+            map,
+            node_limit: None,
+            expanded_nodes: Cell::new(0),
+        }
+    }
+
+    /// Sets the maximum number of nodes expanded by [`a_star_search`].
+    ///
+    /// [`a_star_search`]: GridSolver::a_star_search
+    pub fn set_node_limit(&mut self, node_limit: Option<usize>) {
+        self.node_limit = node_limit;
+    }
+
+    /// Returns a reference to the map.
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns the minimum number of pushes required to push a box from each
+    /// position to the nearest goal.
+    pub fn lower_bounds(&self) -> &HashMap<Vector2<i32>, i32> {
+        &self.lower_bounds
+    }
+
+    /// Returns the number of nodes expanded by the last [`a_star_search`].
+    ///
+    /// [`a_star_search`]: GridSolver::a_star_search
+    pub fn expanded_nodes(&self) -> usize {
+        self.expanded_nodes.get()
+    }
+
+    /// Searches for a push optimal solution using the A* algorithm.
+    ///
+    /// Returns the directions of the player's movements.
+    pub fn a_star_search(&self) -> Result<Vec<<M::Topology as Topology>::Direction>, SearchError> {
+        let mut box_positions: Vec<_> = self.map.box_positions().iter().copied().collect();
+        box_positions.sort_by_key(|position| (position.y, position.x));
+        let state = GridState {
+            box_positions,
+            player_position: self.map.player_position(),
+        };
+        let pushes = a_star_search(self, state, self.node_limit, &self.expanded_nodes)?;
+        Ok(self.construct_directions(pushes))
+    }
+
+    /// Returns the area reachable by the player in the state.
+    fn reachable_area(&self, state: &GridState) -> HashSet<Vector2<i32>> {
+        reachable_area_in::<M::Topology>(state.player_position, |position| {
+            self.map.is_floor(position) && !state.box_positions.contains(&position)
+        })
+    }
+
+    /// Constructs the player's movements from the pushes, each given by the
+    /// position of the box before the push and the direction.
+    fn construct_directions(
+        &self,
+        pushes: Vec<(Vector2<i32>, <M::Topology as Topology>::Direction)>,
+    ) -> Vec<<M::Topology as Topology>::Direction> {
+        let mut map = self.map.clone();
+        let mut directions = Vec::new();
+        for (box_position, push_direction) in pushes {
+            let path = find_path_in::<M::Topology>(
+                map.player_position(),
+                M::Topology::neighbor(box_position, -push_direction),
+                |position| map.can_move(position),
+            )
+            .unwrap();
+            for step in path.windows(2) {
+                let direction = *M::Topology::directions()
+                    .iter()
+                    .find(|&&direction| M::Topology::neighbor(step[0], direction) == step[1])
+                    .unwrap();
+                map.do_move(direction).unwrap();
+                directions.push(direction);
+            }
+            map.do_move(push_direction).unwrap();
+            directions.push(push_direction);
+        }
+        directions
+    }
+}
+
+impl<M: Grid> SearchProblem for GridSolver<M> {
+    type State = GridState;
+    type Key = (Vec<Vector2<i32>>, Vector2<i32>);
+    type Step = (Vector2<i32>, <M::Topology as Topology>::Direction);
+
+    fn key(&self, state: &GridState) -> Self::Key {
+        let area = self.reachable_area(state);
+        (state.box_positions.clone(), normalized_area(&area).unwrap())
+    }
+
+    /// Returns the sum of the lower bounds of the boxes, or `None` if any box
+    /// is in a static deadlock.
+    fn heuristic(&self, state: &GridState) -> Option<i32> {
+        state
+            .box_positions
+            .iter()
+            .map(|position| self.lower_bounds.get(position))
+            .sum()
+    }
+
+    fn is_goal(&self, state: &GridState) -> bool {
+        state
+            .box_positions
+            .iter()
+            .all(|position| self.map.goal_positions().contains(position))
+    }
+
+    fn successors(&self, state: &GridState) -> Vec<(Self::Step, GridState)> {
+        let area = self.reachable_area(state);
+        let mut successors = Vec::new();
+        for (box_index, &box_position) in state.box_positions.iter().enumerate() {
+            for &direction in M::Topology::directions() {
+                let player_position = M::Topology::neighbor(box_position, -direction);
+                let new_box_position = M::Topology::neighbor(box_position, direction);
+                if !area.contains(&player_position)
+                    || !self.map.is_floor(new_box_position)
+                    || state.box_positions.contains(&new_box_position)
+                {
+                    continue;
+                }
+                let mut box_positions = state.box_positions.clone();
+                box_positions[box_index] = new_box_position;
+                box_positions.sort_by_key(|position| (position.y, position.x));
+                successors.push((
+                    (box_position, direction),
+                    GridState {
+                        box_positions,
+                        player_position: box_position,
+                    },
+                ));
+            }
+        }
+        successors
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     hash::{DefaultHasher, Hash, Hasher},
 };
 
@@ -15,22 +15,36 @@ use crate::{
 pub struct State {
     pub player_position: Vector2<i32>,
     pub box_positions: HashSet<Vector2<i32>>,
+    pub box_ids: HashMap<Vector2<i32>, u32>,
 }
 
 impl State {
     /// Returns true if the state is solved.
     pub fn is_solved(&self, solver: &Solver) -> bool {
         self.box_positions == *solver.map().goal_positions()
+            && self
+                .box_positions
+                .iter()
+                .all(|&position| self.box_id(position) == solver.map().goal_id(position))
     }
 
     /// Returns the heuristic value of the state.
     pub fn heuristic(&self, solver: &Solver) -> i32 {
         self.box_positions
             .iter()
-            .map(|box_position| solver.lower_bounds()[box_position])
+            .map(|&box_position| {
+                solver
+                    .lower_bound(box_position, self.box_id(box_position))
+                    .unwrap()
+            })
             .sum()
     }
 
+    /// Returns the ID of the box at the position.
+    pub fn box_id(&self, position: Vector2<i32>) -> u32 {
+        self.box_ids.get(&position).copied().unwrap_or(0)
+    }
+
     /// Normalizes the state.
     pub fn normalize(&mut self, map: &Map) {
         self.player_position = normalized_area(&reachable_area(self.player_position, |position| {
@@ -52,8 +66,17 @@ impl State {
 impl Hash for State {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.player_position.hash(state);
-        for box_position in &self.box_positions {
-            box_position.hash(state);
-        }
+        // Sum the hashes of the boxes, since the iteration order of a
+        // `HashSet` is unspecified
+        let boxes_hash = self
+            .box_positions
+            .iter()
+            .fold(0u64, |boxes_hash, &box_position| {
+                let mut hasher = DefaultHasher::new();
+                box_position.hash(&mut hasher);
+                self.box_id(box_position).hash(&mut hasher);
+                boxes_hash.wrapping_add(hasher.finish())
+            });
+        boxes_hash.hash(state);
     }
 }
@@ -0,0 +1,161 @@
+//! Grid topologies.
+
+use std::{collections::HashSet, fmt::Debug, hash::Hash, ops::Neg};
+
+use nalgebra::Vector2;
+
+use crate::{
+    direction::Direction, error::ActionError, hexoban::HexDirection, trioban::TriDirection,
+};
+
+/// The topology of a grid, which defines the neighbors of each position.
+///
+/// Algorithms generic over the topology, such as
+/// [`reachable_area_in`](crate::path_finding::reachable_area_in) and
+/// [`find_path_in`](crate::path_finding::find_path_in), work for square,
+/// hexagonal and triangular grids.
+pub trait Topology {
+    /// The direction of movement.
+    type Direction: Copy + Eq + Hash + Debug + Neg<Output = Self::Direction> + 'static;
+
+    /// Returns all directions.
+    fn directions() -> &'static [Self::Direction];
+
+    /// Returns the neighbor of the position in the direction.
+    ///
+    /// Moving in a direction and then in the opposite direction returns to the
+    /// original position.
+    fn neighbor(position: Vector2<i32>, direction: Self::Direction) -> Vector2<i32>;
+
+    /// Returns the minimum number of steps between two positions without
+    /// obstacles.
+    fn distance(a: Vector2<i32>, b: Vector2<i32>) -> i32;
+}
+
+/// The square grid used by Sokoban.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct Square;
+
+impl Topology for Square {
+    type Direction = Direction;
+
+    fn directions() -> &'static [Direction] {
+        &[
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+    }
+
+    fn neighbor(position: Vector2<i32>, direction: Direction) -> Vector2<i32> {
+        position + &direction.into()
+    }
+
+    fn distance(a: Vector2<i32>, b: Vector2<i32>) -> i32 {
+        (a.x - b.x).abs() + (a.y - b.y).abs()
+    }
+}
+
+/// The hexagonal grid used by Hexoban.
+///
+/// Positions are in doubled-width coordinates, see [`HexDirection`].
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct Hexagonal;
+
+impl Topology for Hexagonal {
+    type Direction = HexDirection;
+
+    fn directions() -> &'static [HexDirection] {
+        &[
+            HexDirection::East,
+            HexDirection::West,
+            HexDirection::NorthEast,
+            HexDirection::NorthWest,
+            HexDirection::SouthEast,
+            HexDirection::SouthWest,
+        ]
+    }
+
+    fn neighbor(position: Vector2<i32>, direction: HexDirection) -> Vector2<i32> {
+        position + &direction.into()
+    }
+
+    fn distance(a: Vector2<i32>, b: Vector2<i32>) -> i32 {
+        let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+        dy + (dx - dy).max(0) / 2
+    }
+}
+
+/// The triangular grid used by Trioban.
+///
+/// The triangle at `(x, y)` points up if `x + y` is even, and down otherwise,
+/// see [`TriDirection`].
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct Triangular;
+
+impl Topology for Triangular {
+    type Direction = TriDirection;
+
+    fn directions() -> &'static [TriDirection] {
+        &[
+            TriDirection::East,
+            TriDirection::West,
+            TriDirection::NorthEast,
+            TriDirection::NorthWest,
+            TriDirection::SouthEast,
+            TriDirection::SouthWest,
+        ]
+    }
+
+    fn neighbor(position: Vector2<i32>, direction: TriDirection) -> Vector2<i32> {
+        position + &direction.offset(position)
+    }
+
+    fn distance(a: Vector2<i32>, b: Vector2<i32>) -> i32 {
+        // Each step changes either the X or the Y coordinate by one
+        (a.x - b.x).abs() + (a.y - b.y).abs()
+    }
+}
+
+/// A map on a grid with a topology, used by the topology-agnostic algorithms
+/// such as [`GridSolver`](crate::solver::GridSolver).
+pub trait Grid: Clone {
+    /// The topology of the grid.
+    type Topology: Topology;
+
+    /// Returns the dimensions of the map.
+    fn dimensions(&self) -> Vector2<i32>;
+
+    /// Returns the position of the player.
+    fn player_position(&self) -> Vector2<i32>;
+
+    /// Returns the positions of the boxes.
+    fn box_positions(&self) -> &HashSet<Vector2<i32>>;
+
+    /// Returns the positions of the goals.
+    fn goal_positions(&self) -> &HashSet<Vector2<i32>>;
+
+    /// Checks if the position is a floor, i.e. reachable by the player when
+    /// ignoring boxes.
+    fn is_floor(&self, position: Vector2<i32>) -> bool;
+
+    /// Moves the player in the direction, pushing the box in front of the
+    /// player if any.
+    ///
+    /// Returns `true` if a box is pushed.
+    fn do_move(
+        &mut self,
+        direction: <Self::Topology as Topology>::Direction,
+    ) -> Result<bool, ActionError>;
+
+    /// Checks if the player can move to the position.
+    fn can_move(&self, position: Vector2<i32>) -> bool {
+        self.is_floor(position) && !self.box_positions().contains(&position)
+    }
+
+    /// Checks if the map is solved.
+    fn is_solved(&self) -> bool {
+        self.box_positions() == self.goal_positions()
+    }
+}
@@ -0,0 +1,311 @@
+//! Trioban, the Sokoban variant played on a triangular grid.
+//!
+//! Trioban levels use the XSB format, where each character is a triangle. The
+//! triangle at `(x, y)` points up if `x + y` is even and down otherwise, so
+//! each triangle has three neighbors: left, right, and either below or above.
+//!
+//! The player moves and pushes boxes along straight strips of triangles in six
+//! directions. Since each neighbor lies on two strips, the direction of a push
+//! determines where the box goes, not only the neighbor it leaves towards.
+
+use std::{collections::HashSet, fmt, ops::Neg, str::FromStr};
+
+use nalgebra::Vector2;
+
+use crate::{
+    deadlock::calculate_static_deadlocks_in,
+    error::{ActionError, ParseMapError},
+    map::Map,
+    path_finding::reachable_area_in,
+    solver::GridSolver,
+    tiles::Tiles,
+    topology::{Grid, Topology, Triangular},
+};
+
+/// A direction on a triangular grid.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum TriDirection {
+    /// Rightward along the row.
+    East,
+    /// Leftward along the row.
+    West,
+    /// Up and to the right, along a 60° strip.
+    NorthEast,
+    /// Up and to the left, along a 120° strip.
+    NorthWest,
+    /// Down and to the right, along a 120° strip.
+    SouthEast,
+    /// Down and to the left, along a 60° strip.
+    SouthWest,
+}
+
+impl TriDirection {
+    /// Returns an iterator over all directions.
+    pub fn iter() -> std::array::IntoIter<TriDirection, 6> {
+        [
+            Self::East,
+            Self::West,
+            Self::NorthEast,
+            Self::NorthWest,
+            Self::SouthEast,
+            Self::SouthWest,
+        ]
+        .into_iter()
+    }
+
+    /// Returns the offset to the neighbor of the triangle at the position in
+    /// this direction.
+    pub fn offset(self, position: Vector2<i32>) -> Vector2<i32> {
+        let points_up = (position.x + position.y).rem_euclid(2) == 0;
+        match (self, points_up) {
+            (Self::East, _) | (Self::NorthEast, true) | (Self::SouthEast, false) => {
+                Vector2::new(1, 0)
+            }
+            (Self::West, _) | (Self::NorthWest, true) | (Self::SouthWest, false) => {
+                Vector2::new(-1, 0)
+            }
+            (Self::NorthEast | Self::NorthWest, false) => Vector2::new(0, -1),
+            (Self::SouthEast | Self::SouthWest, true) => Vector2::new(0, 1),
+        }
+    }
+}
+
+impl Neg for TriDirection {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Self::East => Self::West,
+            Self::West => Self::East,
+            Self::NorthEast => Self::SouthWest,
+            Self::NorthWest => Self::SouthEast,
+            Self::SouthEast => Self::NorthWest,
+            Self::SouthWest => Self::NorthEast,
+        }
+    }
+}
+
+/// A Trioban map.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TriMap {
+    map: Map,
+}
+
+impl TriMap {
+    /// Returns the tiles at the position, or `None` if it is out of bounds.
+    pub fn get(&self, position: Vector2<i32>) -> Option<&Tiles> {
+        self.map.get(position)
+    }
+
+    /// Marks the positions reachable by the player, ignoring boxes, as floors.
+    fn update_floors(&mut self) {
+        let dimensions = self.map.dimensions();
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                self.map
+                    .get_mut(Vector2::new(x, y))
+                    .unwrap()
+                    .remove(Tiles::Floor);
+            }
+        }
+        let floors = reachable_area_in::<Triangular>(self.map.player_position(), |position| {
+            self.map
+                .get(position)
+                .is_some_and(|tiles| !tiles.intersects(Tiles::Wall))
+        });
+        for position in floors {
+            self.map.get_mut(position).unwrap().insert(Tiles::Floor);
+        }
+    }
+}
+
+impl Grid for TriMap {
+    type Topology = Triangular;
+
+    fn dimensions(&self) -> Vector2<i32> {
+        self.map.dimensions()
+    }
+
+    fn player_position(&self) -> Vector2<i32> {
+        self.map.player_position()
+    }
+
+    fn box_positions(&self) -> &HashSet<Vector2<i32>> {
+        self.map.box_positions()
+    }
+
+    fn goal_positions(&self) -> &HashSet<Vector2<i32>> {
+        self.map.goal_positions()
+    }
+
+    fn is_floor(&self, position: Vector2<i32>) -> bool {
+        self.get(position)
+            .is_some_and(|tiles| tiles.intersects(Tiles::Floor))
+    }
+
+    fn do_move(&mut self, direction: TriDirection) -> Result<bool, ActionError> {
+        let new_player_position = Triangular::neighbor(self.player_position(), direction);
+        if self
+            .get(new_player_position)
+            .is_none_or(|tiles| tiles.intersects(Tiles::Wall))
+        {
+            return Err(ActionError::MoveBlocked);
+        }
+        let pushed = self.map[new_player_position].intersects(Tiles::Box);
+        if pushed {
+            let new_box_position = Triangular::neighbor(new_player_position, direction);
+            if self
+                .get(new_box_position)
+                .is_none_or(|tiles| tiles.intersects(Tiles::Wall | Tiles::Box))
+            {
+                return Err(ActionError::PushBlocked);
+            }
+            self.map
+                .set_box_position(new_player_position, new_box_position);
+        }
+        self.map.set_player_position(new_player_position);
+        Ok(pushed)
+    }
+}
+
+impl FromStr for TriMap {
+    type Err = ParseMapError;
+
+    /// Creates a new `TriMap` from a XSB format string.
+    fn from_str(xsb: &str) -> Result<Self, Self::Err> {
+        let (mut map, _, indent) = Map::parse(xsb, false)?;
+        // Keep the orientation of the triangles after the indentation is trimmed
+        if indent % 2 == 1 {
+            map.resize(map.dimensions() + Vector2::x(), Vector2::x())
+                .unwrap();
+        }
+        let mut instance = Self { map };
+        instance.update_floors();
+        Ok(instance)
+    }
+}
+
+impl fmt::Display for TriMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.map.to_string().lines() {
+            writeln!(f, "{}", line.replace(['_', '-'], " ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// An action on a triangular grid.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum TriAction {
+    /// Move action in a specified direction.
+    Move(TriDirection),
+    /// Push action in a specified direction.
+    Push(TriDirection),
+}
+
+impl TriAction {
+    /// Returns the direction associated with the action.
+    pub fn direction(&self) -> TriDirection {
+        match *self {
+            Self::Move(direction) | Self::Push(direction) => direction,
+        }
+    }
+
+    /// Checks if the action is a push action.
+    pub fn is_push(&self) -> bool {
+        matches!(self, Self::Push(_))
+    }
+}
+
+/// A Trioban level, which records the actions of the player.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TriLevel {
+    map: TriMap,
+    actions: Vec<TriAction>,
+    undone_actions: Vec<TriAction>,
+}
+
+impl TriLevel {
+    /// Creates a new `TriLevel` from a map.
+    pub fn from_map(map: TriMap) -> Self {
+        Self {
+            map,
+            actions: Vec::new(),
+            undone_actions: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the map.
+    pub fn map(&self) -> &TriMap {
+        &self.map
+    }
+
+    /// Returns a reference to the performed actions.
+    pub fn actions(&self) -> &[TriAction] {
+        &self.actions
+    }
+
+    /// Moves the player in the specified direction, pushing the box in front
+    /// of the player if any.
+    ///
+    /// Returns the performed action. The undone actions are discarded.
+    pub fn do_action(&mut self, direction: TriDirection) -> Result<TriAction, ActionError> {
+        let action = self.perform_action(direction)?;
+        self.actions.push(action);
+        self.undone_actions.clear();
+        Ok(action)
+    }
+
+    /// Undoes the last action.
+    pub fn undo_action(&mut self) -> Result<TriAction, ActionError> {
+        let action = self.actions.pop().ok_or(ActionError::NoActions)?;
+        let player_position = self.map.player_position();
+        let prev_player_position = Triangular::neighbor(player_position, -action.direction());
+        self.map.map.set_player_position(prev_player_position);
+        if action.is_push() {
+            let box_position = Triangular::neighbor(player_position, action.direction());
+            self.map.map.set_box_position(box_position, player_position);
+        }
+        self.undone_actions.push(action);
+        Ok(action)
+    }
+
+    /// Redoes the last undone action.
+    pub fn redo_action(&mut self) -> Result<TriAction, ActionError> {
+        let action = self
+            .undone_actions
+            .pop()
+            .ok_or(ActionError::NoUndoneActions)?;
+        self.perform_action(action.direction()).unwrap();
+        self.actions.push(action);
+        Ok(action)
+    }
+
+    /// Moves the player without recording the action.
+    fn perform_action(&mut self, direction: TriDirection) -> Result<TriAction, ActionError> {
+        Ok(if self.map.do_move(direction)? {
+            TriAction::Push(direction)
+        } else {
+            TriAction::Move(direction)
+        })
+    }
+}
+
+impl FromStr for TriLevel {
+    type Err = ParseMapError;
+
+    /// Creates a new `TriLevel` from a XSB format string.
+    fn from_str(xsb: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_map(TriMap::from_str(xsb)?))
+    }
+}
+
+/// Calculates static deadlock positions independent of the player's position.
+///
+/// See [`calculate_static_deadlocks_in`] for details.
+pub fn calculate_static_deadlocks(map: &TriMap) -> HashSet<Vector2<i32>> {
+    calculate_static_deadlocks_in(map)
+}
+
+/// A push optimal solver for Trioban.
+pub type TriSolver = GridSolver<TriMap>;
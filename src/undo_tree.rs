@@ -0,0 +1,185 @@
+//! A tree of actions for branching undo and redo.
+
+use std::ops::DerefMut;
+
+use crate::actions::Actions;
+
+/// A tree of actions for branching undo and redo.
+///
+/// Each node except the root represents a unit of actions which are undone and
+/// redone together, usually a single action. Performing new actions after
+/// undoing creates a new branch instead of discarding the undone actions, so
+/// every explored alternative is preserved. Redo follows the most recently used
+/// branch.
+///
+/// The units are [`Actions`] by default, other sequences of actions such as
+/// [`MultiActions`](crate::multiban::MultiActions) are also supported.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct UndoTree<U = Actions> {
+    nodes: Vec<Node<U>>,
+    current: usize,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Node<U> {
+    actions: U,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The index of the child followed by redo.
+    active_branch: usize,
+}
+
+impl<U> Node<U> {
+    fn new(actions: U, parent: Option<usize>) -> Self {
+        Self {
+            actions,
+            parent,
+            children: Vec::new(),
+            active_branch: 0,
+        }
+    }
+}
+
+impl<T, U> UndoTree<U>
+where
+    T: Clone,
+    U: Clone + PartialEq + Default + DerefMut<Target = Vec<T>>,
+{
+    /// Creates an empty `UndoTree`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records an action performed at the current node and moves to it.
+    ///
+    /// If the current node already has a branch with the same action, the
+    /// branch is reused.
+    pub fn push(&mut self, action: T) {
+        let mut actions = U::default();
+        actions.push(action);
+        self.push_unit(actions);
+    }
+
+    /// Records a unit of actions performed at the current node and moves to
+    /// it. The actions are undone and redone together.
+    ///
+    /// If the current node already has a branch with the same actions, the
+    /// branch is reused.
+    pub fn push_unit(&mut self, actions: U) {
+        debug_assert!(!actions.is_empty(), "actions are empty");
+        let index = self
+            .children(self.current)
+            .position(|node| node.actions == actions);
+        let index = match index {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node::new(actions, Some(self.current)));
+                let child = self.nodes.len() - 1;
+                self.nodes[self.current].children.push(child);
+                self.nodes[self.current].children.len() - 1
+            }
+        };
+        self.nodes[self.current].active_branch = index;
+        self.current = self.nodes[self.current].children[index];
+    }
+
+    /// Moves to the parent node and returns the actions of the current node,
+    /// or `None` if the current node is the root.
+    pub fn undo(&mut self) -> Option<U> {
+        let node = &self.nodes[self.current];
+        let parent = node.parent?;
+        let actions = node.actions.clone();
+        let index = self.nodes[parent]
+            .children
+            .iter()
+            .position(|&child| child == self.current)
+            .unwrap();
+        self.nodes[parent].active_branch = index;
+        self.current = parent;
+        Some(actions)
+    }
+
+    /// Moves to the active branch and returns its actions, or `None` if the
+    /// current node has no branches.
+    pub fn redo(&mut self) -> Option<U> {
+        let actions = self.redo_actions()?.clone();
+        let node = &self.nodes[self.current];
+        self.current = node.children[node.active_branch];
+        Some(actions)
+    }
+
+    /// Returns the actions that would be performed by [`redo`], or `None` if
+    /// the current node has no branches.
+    ///
+    /// [`redo`]: UndoTree::redo
+    pub fn redo_actions(&self) -> Option<&U> {
+        let node = &self.nodes[self.current];
+        let child = *node.children.get(node.active_branch)?;
+        Some(&self.nodes[child].actions)
+    }
+
+    /// Returns the actions of the branches at the current node, in the order
+    /// they were created.
+    pub fn branches(&self) -> Vec<&U> {
+        self.children(self.current)
+            .map(|node| &node.actions)
+            .collect()
+    }
+
+    /// Returns the index of the branch followed by redo, or `None` if the
+    /// current node has no branches.
+    pub fn active_branch(&self) -> Option<usize> {
+        let node = &self.nodes[self.current];
+        (!node.children.is_empty()).then_some(node.active_branch)
+    }
+
+    /// Switches the branch followed by redo.
+    ///
+    /// Returns `false` if the branch does not exist.
+    pub fn switch_branch(&mut self, index: usize) -> bool {
+        let node = &mut self.nodes[self.current];
+        if index >= node.children.len() {
+            return false;
+        }
+        node.active_branch = index;
+        true
+    }
+
+    /// Returns the actions from the root to every leaf of the tree.
+    ///
+    /// The lines are returned in depth-first order, with branches visited in
+    /// the order they were created.
+    pub fn lines(&self) -> Vec<U> {
+        let mut lines = Vec::new();
+        let mut stack = vec![(0, U::default())];
+        while let Some((index, mut actions)) = stack.pop() {
+            let node = &self.nodes[index];
+            actions.extend_from_slice(&node.actions);
+            if node.children.is_empty() {
+                lines.push(actions);
+                continue;
+            }
+            for &child in node.children.iter().rev() {
+                stack.push((child, actions.clone()));
+            }
+        }
+        lines
+    }
+
+    /// Returns an iterator over the children of the node.
+    fn children(&self, index: usize) -> impl Iterator<Item = &Node<U>> + '_ {
+        self.nodes[index]
+            .children
+            .iter()
+            .map(|&child| &self.nodes[child])
+    }
+}
+
+impl<U: Default> Default for UndoTree<U> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node::new(U::default(), None)],
+            current: 0,
+        }
+    }
+}
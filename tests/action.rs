@@ -8,6 +8,10 @@ fn action_from_char() {
     assert_eq!(Action::try_from('U'), Ok(Action::Push(Direction::Up)));
     assert_eq!(Action::try_from('D'), Ok(Action::Push(Direction::Down)));
 
+    assert_eq!(Action::try_from('N'), Ok(Action::Pull(Direction::Up)));
+    assert_eq!(Action::try_from('E'), Ok(Action::Pull(Direction::Right)));
+    assert!(Action::try_from('n').is_err());
+
     assert!(Action::try_from('x').is_err());
 }
 
@@ -15,4 +19,5 @@ fn action_from_char() {
 fn action_to_char() {
     assert_eq!(char::from(Action::Move(Direction::Up)), 'u');
     assert_eq!(char::from(Action::Push(Direction::Up)), 'U');
+    assert_eq!(char::from(Action::Pull(Direction::Up)), 'N');
 }
@@ -1,6 +1,9 @@
 use std::str::FromStr;
 
-use soukoban::{Actions, ParseActionError, ParseActionsError, SecondaryValues};
+use soukoban::{Actions, Level, ParseActionError, ParseActionsError, SecondaryValues};
+
+mod utils;
+use utils::*;
 
 #[test]
 fn actions_from_str() {
@@ -86,3 +89,26 @@ fn scoring_metrics() {
     assert_eq!(pushing_sessions, 6);
     assert_eq!(player_lines, 20);
 }
+
+#[test]
+fn transform() {
+    let mut map = load_level_from_file("assets/Microban_155.xsb", 3)
+        .map()
+        .clone();
+    let mut actions = Actions::from_str("ruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurDD").unwrap();
+    for i in 0..8 {
+        if i == 4 {
+            map.flip();
+            actions.flip();
+        }
+        map.rotate();
+        actions.rotate();
+
+        let mut level = Level::from_map(map.clone());
+        level
+            .do_actions(actions.iter().map(|action| action.direction()))
+            .unwrap();
+        assert!(level.is_solved());
+        assert_eq!(level.actions(), &actions);
+    }
+}
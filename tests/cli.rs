@@ -0,0 +1,233 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+};
+
+use indoc::indoc;
+
+const COLLECTION: &str = indoc! {"
+    ; Test collection
+    ; Two small levels.
+
+    ; 1
+
+    #####
+    #@$.#
+    #####
+    title: A & B
+    solution: R
+
+    ; 2
+
+    ######
+    #@ $.#
+    ######
+    solution: rR
+"};
+
+fn soukoban(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_soukoban"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Creates an empty temporary directory for the test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("soukoban-{}-{name}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn path_str(dir: &Path, name: &str) -> String {
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+#[test]
+fn solve() {
+    let dir = temp_dir("solve");
+    let input = path_str(&dir, "input.xsb");
+    let output = path_str(&dir, "output.xsb");
+    let collection = COLLECTION
+        .lines()
+        .filter(|line| !line.starts_with("solution"))
+        .map(|line| line.to_string() + "\n")
+        .collect::<String>();
+    fs::write(&input, &collection).unwrap();
+
+    assert!(soukoban(&["solve", &input, "--output", &output])
+        .status
+        .success());
+    assert_eq!(fs::read_to_string(&input).unwrap(), collection);
+    let result = fs::read_to_string(&output).unwrap();
+    for line in ["; Test collection", "; Two small levels.", "; 1", "; 2"] {
+        assert!(result.lines().any(|result_line| result_line == line));
+    }
+    assert!(soukoban(&["verify", &output]).status.success());
+
+    // The solutions are written back to the input file by default
+    assert!(!soukoban(&["verify", &input]).status.success());
+    assert!(soukoban(&["solve", &input]).status.success());
+    assert_eq!(fs::read_to_string(&input).unwrap(), result);
+
+    for time_limit in ["0", "-1", "NaN", "1e300"] {
+        let result = soukoban(&["solve", &input, &format!("--time-limit={time_limit}")]);
+        assert!(!result.status.success());
+        assert!(String::from_utf8(result.stderr)
+            .unwrap()
+            .contains("--time-limit"));
+    }
+}
+
+#[test]
+fn convert() {
+    let dir = temp_dir("convert");
+    let input = path_str(&dir, "input.xsb");
+    fs::write(&input, COLLECTION).unwrap();
+
+    for extension in ["xsb", "rle", "sok", "slc"] {
+        let converted = path_str(&dir, &format!("converted.{extension}"));
+        let output = path_str(&dir, &format!("output-{extension}.xsb"));
+        assert!(soukoban(&["convert", &input, &converted]).status.success());
+        assert!(soukoban(&["convert", &converted, &output]).status.success());
+
+        let result = fs::read_to_string(&output).unwrap();
+        assert!(result.contains("title: A & B"));
+        assert!(result.contains("solution: R\n"));
+        assert!(result.contains("solution: rR\n"));
+        assert!(soukoban(&["verify", &output]).status.success());
+    }
+
+    // The title of SOK levels is written once, before the map
+    let sok = path_str(&dir, "converted.sok");
+    let round_trip = path_str(&dir, "round-trip.sok");
+    assert!(soukoban(&["convert", &sok, &round_trip]).status.success());
+    let result = fs::read_to_string(&round_trip).unwrap();
+    assert_eq!(result, fs::read_to_string(&sok).unwrap());
+    assert_eq!(result.matches("A & B").count(), 1);
+    assert!(result.contains("A & B\n#####\n"));
+
+    // SLC files are parsed as XML
+    let slc = path_str(&dir, "input.slc");
+    let output = path_str(&dir, "output-slc-input.xsb");
+    fs::write(
+        &slc,
+        indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <!-- Exported by another program -->
+            <SokobanLevels>
+              <LevelCollection>
+                <Level Id='A &amp; B' Width="5" Height="3">
+                  <L>#####</L><L>#@$.#</L>
+                  <!-- The last row -->
+                  <L><![CDATA[#####]]></L>
+                </Level>
+              </LevelCollection>
+            </SokobanLevels>
+        "#},
+    )
+    .unwrap();
+    assert!(soukoban(&["convert", &slc, &output]).status.success());
+    let result = fs::read_to_string(&output).unwrap();
+    assert!(result.starts_with("#####\n#@$.#\n#####\n"));
+    assert!(result.contains("title: A & B"));
+    fs::write(&slc, "<SokobanLevels><Level>").unwrap();
+    assert!(!soukoban(&["convert", &slc, &output]).status.success());
+
+    // Comments are kept by the text formats
+    let output = path_str(&dir, "output-rle.xsb");
+    assert!(fs::read_to_string(output)
+        .unwrap()
+        .starts_with("; Test collection\n; Two small levels.\n\n; 1\n"));
+}
+
+#[test]
+fn dedupe() {
+    let dir = temp_dir("dedupe");
+    let input = path_str(&dir, "input.xsb");
+    let output = path_str(&dir, "output.xsb");
+    // The third level is the first one flipped horizontally
+    let collection = COLLECTION.to_string() + "\n#####\n#.$@#\n#####\n";
+    fs::write(&input, collection).unwrap();
+
+    let result = soukoban(&["dedupe", &input, "--output", &output]);
+    assert!(result.status.success());
+    assert_eq!(
+        String::from_utf8(result.stdout).unwrap(),
+        "removed 1 duplicate levels\n"
+    );
+    let result = fs::read_to_string(&output).unwrap();
+    assert!(result.contains("#@$.#"));
+    assert!(result.contains("#@ $.#"));
+    assert!(!result.contains("#.$@#"));
+}
+
+#[test]
+fn stats() {
+    let dir = temp_dir("stats");
+    let input = path_str(&dir, "input.xsb");
+    fs::write(
+        &input,
+        COLLECTION.replace("solution: R\n", "solution: R?\n"),
+    )
+    .unwrap();
+
+    // Invalid solutions are reported without aborting
+    let result = soukoban(&["stats", &input]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .starts_with("A & B: "));
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.lines().nth(1).unwrap().starts_with("#2\t2\t1\t"));
+}
+
+#[test]
+fn bench() {
+    let dir = temp_dir("bench");
+    let input = path_str(&dir, "input.xsb");
+    let baseline = path_str(&dir, "baseline.json");
+    // Levels with the same title are still compared separately
+    fs::write(&input, COLLECTION.replace("solution: rR", "title: A & B")).unwrap();
+
+    let result = soukoban(&["bench", &input, "--json", &baseline]);
+    assert!(result.status.success());
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("level "));
+    assert!(lines[1].starts_with("A & B "));
+    assert!(lines[3].starts_with("solved 2/2, "));
+
+    assert!(soukoban(&["bench", &input, "--baseline", &baseline])
+        .status
+        .success());
+
+    let mut report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&baseline).unwrap()).unwrap();
+    let nodes = report["levels"][1]["expanded_nodes"].as_u64().unwrap();
+    report["levels"][1]["expanded_nodes"] = 0.into();
+    report["levels"][1]["moves"] = 1.into();
+    fs::write(&baseline, report.to_string()).unwrap();
+
+    let result = soukoban(&["bench", &input, "--baseline", &baseline]);
+    assert!(!result.status.success());
+    let regressions: Vec<_> = String::from_utf8(result.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("regression: "))
+        .map(str::to_string)
+        .collect();
+    assert_eq!(
+        regressions,
+        [
+            "A & B: solution grew from 1/1 to 2/1 (moves/pushes)".to_string(),
+            format!("A & B: expanded nodes grew from 0 to {nodes}"),
+        ]
+    );
+}
@@ -1,4 +1,7 @@
-use soukoban::deadlock;
+use std::str::FromStr;
+
+use nalgebra::Vector2;
+use soukoban::{deadlock, rules::MovementRules, Level};
 
 mod utils;
 use utils::*;
@@ -11,3 +14,29 @@ fn calculate_static_deadlocks() {
     let map = load_level_from_file("assets/BoxWorld_100.xsb", 9).into();
     assert_eq!(deadlock::calculate_static_deadlocks(&map).len(), 17);
 }
+
+#[test]
+fn calculate_static_deadlocks_with_ids() {
+    let level =
+        Level::from_str("#######|#.$@$.#|#######\nBoxSequence: 1 2\nGoalSequence: 2 1").unwrap();
+    let map = level.map();
+    assert!(deadlock::calculate_static_deadlocks(map).is_empty());
+    for rules in [MovementRules::PushOnly, MovementRules::PushAndPull] {
+        let dead_positions = deadlock::calculate_static_deadlocks_with_rules(map, rules, 1);
+        assert!(dead_positions.contains(&Vector2::new(1, 1)));
+        assert!(!dead_positions.contains(&Vector2::new(5, 1)));
+    }
+}
+
+#[test]
+fn calculate_static_deadlocks_with_rules() {
+    let map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    assert_eq!(
+        deadlock::calculate_static_deadlocks_with_rules(&map, MovementRules::PushOnly, 0),
+        deadlock::calculate_static_deadlocks(&map)
+    );
+    assert!(
+        deadlock::calculate_static_deadlocks_with_rules(&map, MovementRules::PushAndPull, 0)
+            .is_empty()
+    );
+}
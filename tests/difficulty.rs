@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use soukoban::{difficulty::estimate, Map};
+
+mod utils;
+use utils::*;
+
+#[test]
+fn estimate_solved() {
+    let report = estimate(&load_level_from_file("assets/Microban_155.xsb", 1).into());
+    assert!(report.expanded_nodes <= 2000);
+    let (moves, pushes) = (report.moves.unwrap(), report.pushes.unwrap());
+    assert!(pushes > 0 && moves >= pushes);
+    let secondary_values = report.secondary_values.unwrap();
+    assert!(secondary_values.box_changes as usize <= pushes);
+    assert!(secondary_values.pushing_sessions as usize <= pushes);
+    assert!(report.score.is_finite() && report.score >= 0.0);
+
+    // Known easy levels score lower than known hard levels
+    let medium = estimate(&load_level_from_file("assets/Sasquatch_50.xsb", 1).into());
+    let hard = estimate(&load_level_from_file("assets/SokHard_163.xsb", 1).into());
+    assert!(report.score < medium.score);
+    assert!(medium.score < hard.score);
+}
+
+#[test]
+fn monotonicity() {
+    // The same map with the box further away from the goal is harder
+    let scores: Vec<_> = ["#  @$.#", "# @$ .#", "#@$  .#"]
+        .iter()
+        .map(|row| {
+            let map = Map::from_str(&format!("#######\n{row}\n#######")).unwrap();
+            estimate(&map).score
+        })
+        .collect();
+    assert!(scores.is_sorted_by(|a, b| a < b));
+}
+
+#[test]
+fn estimate_unsolved() {
+    let report = estimate(&load_level_from_file("assets/SokHard_163.xsb", 1).into());
+    assert_eq!(report.expanded_nodes, 2000);
+    assert_eq!(report.pushes, None);
+    assert_eq!(report.secondary_values, None);
+    assert!(report.branching_factor > 1.0);
+}
+
+#[test]
+fn calibration() {
+    let average_score = |path: &str, len: usize| {
+        let ids: Vec<_> = (1..=len).step_by(len / 6).collect();
+        ids.iter()
+            .map(|&id| estimate(&load_level_from_file(path, id).into()).score)
+            .sum::<f64>()
+            / ids.len() as f64
+    };
+    let microban = average_score("assets/Microban_155.xsb", 155);
+    let sasquatch = average_score("assets/Sasquatch_50.xsb", 50);
+    let sokhard = average_score("assets/SokHard_163.xsb", 163);
+    assert!(microban < sasquatch);
+    assert!(sasquatch < sokhard);
+}
@@ -0,0 +1,59 @@
+use nalgebra::Vector2;
+use soukoban::{
+    generator::{Generator, GeneratorOptions},
+    solver::{Solver, Strategy},
+    GeneratorError,
+};
+
+#[test]
+fn generate() {
+    let options = GeneratorOptions {
+        dimensions: Vector2::new(8, 8),
+        box_count: 2,
+        seed: 1,
+        min_pushes: 6,
+    };
+    let mut generator = Generator::new(options).unwrap();
+    for _ in 0..5 {
+        let map = generator.generate().unwrap();
+        assert_eq!(map.box_positions().len(), 2);
+        assert!(map.validate().is_empty());
+        let solution = Solver::new(map.clone(), Strategy::OptimalPush)
+            .a_star_search()
+            .unwrap();
+        assert!(solution.pushes() >= 6);
+        assert!(map.verify(&solution).is_ok());
+    }
+
+    // The generator is deterministic
+    assert_eq!(
+        Generator::new(options).unwrap().generate(),
+        Generator::new(options).unwrap().generate()
+    );
+    assert_ne!(
+        Generator::new(options).unwrap().generate(),
+        Generator::new(GeneratorOptions { seed: 2, ..options })
+            .unwrap()
+            .generate()
+    );
+}
+
+#[test]
+fn invalid_options() {
+    let options = GeneratorOptions {
+        dimensions: Vector2::new(2, 8),
+        ..Default::default()
+    };
+    assert_eq!(
+        Generator::new(options).unwrap_err(),
+        GeneratorError::DimensionsTooSmall
+    );
+    let options = GeneratorOptions {
+        box_count: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        Generator::new(options).unwrap_err(),
+        GeneratorError::NoBoxes
+    );
+}
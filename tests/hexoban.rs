@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use nalgebra::Vector2;
+use soukoban::{
+    hexoban::*,
+    path_finding::{find_path_in, reachable_area_in},
+    topology::{Grid, Hexagonal, Topology},
+    ActionError, ParseMapError, SearchError,
+};
+
+const LEVEL: &str = "   # # # # #
+  # - - - - #
+ # - $ . - - #
+# - @ - $ . - #
+ # - - - - - #
+  # # # # # #
+";
+
+#[test]
+fn parse() {
+    let map = HexMap::from_str(LEVEL).unwrap();
+    assert_eq!(map.dimensions(), Vector2::new(15, 6));
+    assert_eq!(map.player_position(), Vector2::new(4, 3));
+    assert_eq!(map.box_positions().len(), 2);
+    assert!(map.goal_positions().contains(&Vector2::new(7, 2)));
+    assert!(!map.in_bounds(Vector2::new(4, 2)));
+    assert_eq!(map.to_string(), LEVEL);
+
+    // Floors may also be written as spaces
+    assert_eq!(HexMap::from_str(&LEVEL.replace('-', " ")).unwrap(), map);
+
+    assert_eq!(
+        HexMap::from_str(" # #\n#@$.#\n # #"),
+        Err(ParseMapError::MisalignedTile(Vector2::new(1, 1)))
+    );
+}
+
+#[test]
+fn round_trip() {
+    let mut map = HexMap::from_str(LEVEL).unwrap();
+    map.do_move(HexDirection::East).unwrap();
+    map.do_move(HexDirection::East).unwrap();
+    // Floors are written distinctly from the positions between the cells
+    let string = map.to_string();
+    assert_eq!(string.lines().nth(3), Some("# - - - @ * - #"));
+    assert_eq!(HexMap::from_str(&string).unwrap(), map);
+}
+
+#[test]
+fn do_move() {
+    let mut map = HexMap::from_str(LEVEL).unwrap();
+    assert_eq!(map.do_move(HexDirection::NorthWest), Ok(false));
+    assert_eq!(map.do_move(HexDirection::East), Ok(true));
+    assert_eq!(map.player_position(), Vector2::new(5, 2));
+    assert!(map.box_positions().contains(&Vector2::new(7, 2)));
+    assert_eq!(map.do_move(HexDirection::East), Ok(true));
+    assert_eq!(map.do_move(HexDirection::East), Ok(true));
+    assert_eq!(
+        map.do_move(HexDirection::East),
+        Err(ActionError::PushBlocked)
+    );
+    assert_eq!(map.do_move(HexDirection::NorthEast), Ok(false));
+    assert_eq!(
+        map.do_move(HexDirection::NorthEast),
+        Err(ActionError::MoveBlocked)
+    );
+    assert!(!map.is_solved());
+}
+
+#[test]
+fn path_finding() {
+    let map = HexMap::from_str(LEVEL).unwrap();
+    assert_eq!(
+        Hexagonal::distance(Vector2::new(4, 3), Vector2::new(10, 1)),
+        4
+    );
+    let area =
+        reachable_area_in::<Hexagonal>(map.player_position(), |position| map.can_move(position));
+    assert_eq!(area.len(), 18);
+    let path = find_path_in::<Hexagonal>(map.player_position(), Vector2::new(10, 1), |position| {
+        map.can_move(position)
+    })
+    .unwrap();
+    assert_eq!(path.len() - 1, 4);
+}
+
+#[test]
+fn static_deadlocks() {
+    let map = HexMap::from_str(LEVEL).unwrap();
+    let deadlocks = calculate_static_deadlocks(&map);
+    assert!(deadlocks.contains(&Vector2::new(4, 1)));
+    assert!(deadlocks.contains(&Vector2::new(2, 3)));
+    assert!(!deadlocks.contains(&Vector2::new(5, 2)));
+    assert!(!deadlocks.contains(&Vector2::new(7, 2)));
+}
+
+#[test]
+fn solver() {
+    let map = HexMap::from_str(LEVEL).unwrap();
+    let solver = HexSolver::new(map.clone());
+    let solution = solver.a_star_search().unwrap();
+
+    let mut replay = map;
+    let mut pushes = 0;
+    for direction in solution {
+        if replay.do_move(direction).unwrap() {
+            pushes += 1;
+        }
+    }
+    assert!(replay.is_solved());
+    assert_eq!(pushes, 2);
+
+    let mut solver = HexSolver::new(HexMap::from_str(LEVEL).unwrap());
+    solver.set_node_limit(Some(0));
+    assert_eq!(solver.a_star_search(), Err(SearchError::LimitExceeded));
+}
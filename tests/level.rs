@@ -1,7 +1,11 @@
 use std::{fs, str::FromStr};
 
 use indoc::indoc;
-use soukoban::{Level, ParseLevelError, ParseMapError};
+use nalgebra::Vector2;
+use soukoban::{
+    direction::Direction, event::Event, path_finding::Optimization, rules::MovementRules, Action,
+    ActionError, Actions, Level, ParseLevelError, ParseMapError, PlayOptions,
+};
 
 mod utils;
 use utils::*;
@@ -93,7 +97,7 @@ fn metadata() {
         comment-end:
         author: level author
     "#;
-    let level = Level::from_str(level_str).unwrap();
+    let mut level = Level::from_str(level_str).unwrap();
     assert_eq!(level.metadata()["tile"], "level title");
     assert_eq!(level.metadata()["author"], "level author");
     assert_eq!(
@@ -106,6 +110,16 @@ fn metadata() {
             comment
         "}
     );
+
+    level
+        .metadata_mut()
+        .insert("solution".to_string(), "R".to_string());
+    let level = Level::from_str(&level.to_string()).unwrap();
+    assert_eq!(level.metadata()["solution"], "R");
+
+    let level = Level::from_str("; Level 1\n#####\n#@$.#\n#####").unwrap();
+    let level = Level::from_str(&level.to_string()).unwrap();
+    assert_eq!(level.metadata()["comments"], "Level 1\n");
 }
 
 #[test]
@@ -167,6 +181,261 @@ fn create_level_with_rle_xsb() {
     );
 }
 
+#[test]
+fn undo_tree() {
+    let mut level = Level::from_str(OPEN).unwrap();
+    assert_eq!(level.undo_action(), Err(ActionError::NoActions));
+    assert_eq!(level.redo_action(), Err(ActionError::NoUndoneActions));
+
+    level.do_actions([Direction::Right, Direction::Up]).unwrap();
+    level.undo_action().unwrap();
+    level.undo_action().unwrap();
+    level.do_action(Direction::Down).unwrap();
+    level.undo_action().unwrap();
+    assert_eq!(
+        level.undo_tree().branches(),
+        [
+            &Actions(vec![Action::Push(Direction::Right)]),
+            &Actions(vec![Action::Move(Direction::Down)])
+        ]
+    );
+
+    // Redo follows the most recent branch
+    assert_eq!(level.undo_tree().active_branch(), Some(1));
+    level.redo_action().unwrap();
+    assert_eq!(level.actions().to_string(), "d");
+    level.undo_action().unwrap();
+
+    assert_eq!(level.switch_branch(2), Err(ActionError::NoBranch));
+    level.switch_branch(0).unwrap();
+    level.redo_action().unwrap();
+    level.redo_action().unwrap();
+    assert_eq!(level.actions().to_string(), "Ru");
+    assert_eq!(level.redo_action(), Err(ActionError::NoUndoneActions));
+
+    // Performing the same action as an existing branch reuses it
+    level.undo_action().unwrap();
+    level.undo_action().unwrap();
+    level.do_action(Direction::Right).unwrap();
+    assert_eq!(
+        level.undo_tree().lines(),
+        [
+            Actions::from_str("Ru").unwrap(),
+            Actions::from_str("d").unwrap()
+        ]
+    );
+}
+
+#[test]
+fn play_options() {
+    use Direction::*;
+
+    let mut level = Level::from_str(OPEN).unwrap();
+    level.do_actions([Down, Up]).unwrap();
+    assert_eq!(level.actions().to_string(), "du");
+    assert_eq!(level.timestamps(), [None, None]);
+
+    let mut level = Level::from_str(OPEN).unwrap();
+    level.set_play_options(PlayOptions {
+        undo_on_reversal: true,
+        ..Default::default()
+    });
+    level.do_actions([Down, Up]).unwrap();
+    assert!(level.actions().is_empty());
+
+    let mut level = Level::from_str(OPEN).unwrap();
+    level.set_play_options(PlayOptions {
+        reject_static_deadlocks: true,
+        record_timestamps: true,
+        ..Default::default()
+    });
+    level.do_actions([Down, Right]).unwrap();
+    assert_eq!(level.do_action(Up), Err(ActionError::PushIntoDeadlock));
+    assert_eq!(level.actions().to_string(), "dr");
+    assert!(level.timestamps().iter().all(Option::is_some));
+    level.undo_action().unwrap();
+    assert_eq!(level.timestamps().len(), 1);
+}
+
+#[test]
+fn movement_rules() {
+    use Direction::*;
+
+    let mut level = Level::from_str("#######|#     #|# .$ @#|#######").unwrap();
+    assert_eq!(level.do_pull(Left), Err(ActionError::PullNotAllowed));
+
+    level.set_movement_rules(MovementRules::PullOnly);
+    level.do_action(Left).unwrap();
+    assert_eq!(level.do_action(Left), Err(ActionError::MoveBlocked));
+    assert_eq!(level.do_pull(Up), Err(ActionError::NoBoxToPull));
+    level.do_actions([Up, Left, Left, Down]).unwrap();
+    let events = level.do_pull(Left).unwrap();
+    assert!(events.contains(&Event::Solved));
+    assert_eq!(level.actions().to_string(), "lulldW");
+
+    level.undo_action().unwrap();
+    assert_eq!(level.map().player_position(), Vector2::new(2, 2));
+    assert!(level.map().box_positions().contains(&Vector2::new(3, 2)));
+    level.redo_action().unwrap();
+    assert!(level.is_solved());
+
+    // Boxes along walls are not dead when they can be pulled
+    let mut level = Level::from_str("######|#    #|#   .#|#@$  #|######").unwrap();
+    level.set_play_options(PlayOptions {
+        reject_static_deadlocks: true,
+        ..Default::default()
+    });
+    assert_eq!(level.do_action(Right), Err(ActionError::PushIntoDeadlock));
+    level.set_movement_rules(MovementRules::PushAndPull);
+    level.do_action(Right).unwrap();
+}
+
+#[test]
+fn sokoban_plus() {
+    use Direction::*;
+
+    let xsb = indoc! {"
+        #######
+        #.$@$.#
+        #######
+        BoxSequence: 1 2
+        GoalSequence: 1 2
+    "};
+    let mut level = Level::from_str(xsb).unwrap();
+    assert_eq!(level.map().box_id(Vector2::new(2, 1)), 1);
+    assert_eq!(level.map().goal_id(Vector2::new(5, 1)), 2);
+    level.do_actions([Left, Right, Right]).unwrap();
+    assert!(level.is_solved());
+
+    // The boxes must be swapped, which is impossible in a corridor
+    let mut level =
+        Level::from_str(&xsb.replace("GoalSequence: 1 2", "GoalSequence: 2 1")).unwrap();
+    level.do_actions([Left, Right, Right]).unwrap();
+    assert!(!level.is_solved());
+    assert!(level.map().verify(&level.actions().clone()).is_err());
+
+    level.undo_action().unwrap();
+    level.undo_action().unwrap();
+    level.undo_action().unwrap();
+    level.set_play_options(PlayOptions {
+        reject_static_deadlocks: true,
+        ..Default::default()
+    });
+    assert_eq!(level.do_action(Left), Err(ActionError::PushIntoDeadlock));
+
+    // The sequences follow the boxes and goals when the map is transformed
+    let mut level = Level::from_str(xsb).unwrap();
+    level.map_mut().flip();
+    let flipped_level = Level::from_str(&level.to_string()).unwrap();
+    assert_eq!(flipped_level.map(), level.map());
+    assert_eq!(flipped_level.map().box_sequence(), [2, 1]);
+    assert_eq!(flipped_level.map().goal_sequence(), [2, 1]);
+
+    assert_eq!(
+        Level::from_str(&xsb.replace("1 2\nGoal", "1 x\nGoal")).unwrap_err(),
+        ParseLevelError::InvalidSequence("1 x".to_string())
+    );
+    assert_eq!(
+        Level::from_str(&xsb.replace("GoalSequence: 1 2", "GoalSequence: 1 3")).unwrap_err(),
+        ParseMapError::BoxGoalMismatch.into()
+    );
+}
+
+#[test]
+fn events() {
+    let mut level = Level::from_str(OPEN).unwrap();
+    assert_eq!(
+        level.do_action(Direction::Up).unwrap(),
+        [Event::PlayerMoved {
+            from: Vector2::new(2, 2),
+            to: Vector2::new(2, 1)
+        }]
+    );
+    level.undo_action().unwrap();
+
+    let push_events = [
+        Event::BoxMoved {
+            from: Vector2::new(3, 2),
+            to: Vector2::new(4, 2),
+        },
+        Event::BoxLandedOnGoal(Vector2::new(4, 2)),
+        Event::PlayerMoved {
+            from: Vector2::new(2, 2),
+            to: Vector2::new(3, 2),
+        },
+        Event::Solved,
+    ];
+    assert_eq!(level.do_action(Direction::Right).unwrap(), push_events);
+    assert_eq!(
+        level.undo_action().unwrap(),
+        [
+            Event::Undone(Action::Push(Direction::Right)),
+            Event::PlayerMoved {
+                from: Vector2::new(3, 2),
+                to: Vector2::new(2, 2),
+            },
+            Event::BoxMoved {
+                from: Vector2::new(4, 2),
+                to: Vector2::new(3, 2),
+            },
+            Event::BoxLeftGoal(Vector2::new(4, 2)),
+        ]
+    );
+    let redo_events = level.redo_action().unwrap();
+    assert_eq!(
+        redo_events[0],
+        Event::Redone(Action::Push(Direction::Right))
+    );
+    assert_eq!(redo_events[1..], push_events);
+}
+
+#[test]
+fn push_box_to() {
+    let mut level = load_level_from_file("assets/Microban_155.xsb", 3);
+    assert_eq!(
+        level.push_box_to(Vector2::new(1, 1), Vector2::new(2, 4), Optimization::Moves),
+        Err(ActionError::NoBox)
+    );
+    assert_eq!(
+        level.push_box_to(Vector2::new(6, 3), Vector2::new(6, 4), Optimization::Moves),
+        Err(ActionError::Unreachable)
+    );
+    level
+        .push_box_to(Vector2::new(6, 2), Vector2::new(2, 4), Optimization::Moves)
+        .unwrap();
+    assert_eq!(level.actions().to_string(), "ruuLLLulDDrdL");
+    assert!(level.map().box_positions().contains(&Vector2::new(2, 4)));
+
+    // The actions are undone and redone as a single unit
+    level.undo_action().unwrap();
+    assert!(level.actions().is_empty());
+    level.redo_action().unwrap();
+    assert_eq!(level.actions().to_string(), "ruuLLLulDDrdL");
+
+    let mut level = Level::from_str(OPEN).unwrap();
+    level.set_play_options(PlayOptions {
+        reject_static_deadlocks: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        level.push_box_to(Vector2::new(3, 2), Vector2::new(1, 1), Optimization::Moves),
+        Err(ActionError::PushIntoDeadlock)
+    );
+    assert!(level.actions().is_empty());
+    level
+        .push_box_to(Vector2::new(3, 2), Vector2::new(4, 2), Optimization::Moves)
+        .unwrap();
+
+    // Boxes can not be pushed when only pulls are allowed
+    let mut level = Level::from_str(OPEN).unwrap();
+    level.set_movement_rules(MovementRules::PullOnly);
+    assert_eq!(
+        level.push_box_to(Vector2::new(3, 2), Vector2::new(4, 2), Optimization::Moves),
+        Err(ActionError::PushNotAllowed)
+    );
+    assert!(level.actions().is_empty());
+}
+
 // Simplest level
 const SIMPLEST: &str = r#"
     #####
@@ -179,3 +448,12 @@ const MICROBAN_3_RLE: &str = "--4#|3#--4#|#5-$-#|#-#--#$-#|#-.-.#@-#|9#";
 
 // Microban II #132
 const MICROBAN2_132_RLE: &str = "18-5#|12-5#-#3-#|12-#3-3#-#-#|6-5#-#-#7-#|5#-#3-#-#3-4#-##|#3-3#-#-#-3#-#--#-#|#-#4-@--#3-#-#--#-3#|#3-4#$6#-4#3-#|3#-#--#-.6-#4-#-#|--#-#--#--##--#4-#3-#|-##-5#--##4-#-5#|-#9-##--3#-#|-#-#-3#-#--5#--#-5#|-#3-#-#4-#-#4-#-#3-#|-5#-#--5#--#-3#-#-#|7-#-3#--##9-#|3-5#-#4-##--5#-##|3-#3-#4-#--##--#--#-#|3-#-#4-#8-#--#-3#|3-#3-4#-6#-4#3-#|3-3#-#--#-#3-#7-#-#|5-#-#--#-3#-#-#-3#3-#|4-##-4#3-#-#3-#-5#|4-#7-#-#-5#|4-#-#-3#3-#|4-#3-#-5#|4-5#";
+
+// Open level
+const OPEN: &str = r#"
+    #######
+    #     #
+    # @$. #
+    #     #
+    #######
+"#;
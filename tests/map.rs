@@ -2,7 +2,10 @@ use std::str::FromStr;
 
 use indoc::indoc;
 use nalgebra::Vector2;
-use soukoban::{tiles::Tiles, Actions, Map, ParseMapError};
+use soukoban::{
+    rules::MovementRules, tiles::Tiles, Actions, EditError, Map, ParseMapError, ValidationError,
+    VerifiedSolution, VerifyError,
+};
 
 mod utils;
 use utils::*;
@@ -88,6 +91,119 @@ fn map_from_actions() {
     );
 }
 
+#[test]
+fn verify() {
+    // Microban #3
+    let map = load_level_from_file("assets/Microban_155.xsb", 3)
+        .map()
+        .clone();
+    let actions = Actions::from_str("ruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurDD").unwrap();
+    let VerifiedSolution {
+        moves,
+        pushes,
+        pulls,
+        secondary_values,
+    } = map.verify(&actions).unwrap();
+    assert_eq!(moves, 43);
+    assert_eq!(pushes, 15);
+    assert_eq!(pulls, 0);
+    assert_eq!(secondary_values, actions.secondary_values());
+
+    // Reversal moves are not interpreted as undo actions
+    let actions = Actions::from_str("rlruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurDD").unwrap();
+    assert_eq!(map.verify(&actions).unwrap().moves, 45);
+
+    assert_eq!(
+        map.verify(&Actions::from_str("ruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurD").unwrap()),
+        Err(VerifyError::Unsolved)
+    );
+    assert_eq!(
+        map.verify(&Actions::from_str("ruuLLLLrrrrddd").unwrap()),
+        Err(VerifyError::MoveBlocked(13))
+    );
+    assert_eq!(
+        map.verify(&Actions::from_str("ruulLLL").unwrap()),
+        Err(VerifyError::PushDeclaredAsMove(3))
+    );
+    assert_eq!(
+        map.verify(&Actions::from_str("rU").unwrap()),
+        Err(VerifyError::MoveDeclaredAsPush(1))
+    );
+    assert_eq!(
+        map.verify(&Actions::from_str("ruuLLLLLL").unwrap()),
+        Err(VerifyError::PushBlocked(8))
+    );
+
+    // Pulls
+    let map = Map::from_str("#######|#     #|# .$ @#|#######").unwrap();
+    let actions = Actions::from_str("ullldW").unwrap();
+    assert_eq!(map.verify(&actions), Err(VerifyError::PullNotAllowed(5)));
+    let solution = map
+        .verify_with_rules(&actions, MovementRules::PullOnly)
+        .unwrap();
+    assert_eq!((solution.pushes, solution.pulls), (0, 1));
+    assert_eq!(
+        map.verify_with_rules(&Actions::from_str("lL").unwrap(), MovementRules::PullOnly),
+        Err(VerifyError::PushNotAllowed(1))
+    );
+    assert_eq!(
+        map.verify_with_rules(&Actions::from_str("W").unwrap(), MovementRules::PushAndPull),
+        Err(VerifyError::MoveDeclaredAsPull(0))
+    );
+    assert_eq!(
+        map.verify_with_rules(
+            &Actions::from_str("lW").unwrap(),
+            MovementRules::PushAndPull
+        ),
+        Err(VerifyError::MoveBlocked(1))
+    );
+
+    // Pulls are rejected on standard levels
+    let map = Map::from_str("#####|# +$#|#####").unwrap();
+    assert_eq!(
+        map.verify(&Actions::from_str("W").unwrap()),
+        Err(VerifyError::PullNotAllowed(0))
+    );
+}
+
+#[test]
+fn replay() {
+    // Microban #3
+    let map = load_level_from_file("assets/Microban_155.xsb", 3)
+        .map()
+        .clone();
+    let actions = Actions::from_str("ruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurDD").unwrap();
+    let snapshots = map.replay(&actions).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(snapshots.len(), actions.len());
+    assert_eq!(snapshots[0].player_position, Vector2::new(7, 4));
+    assert_eq!(snapshots[0].moved_box, None);
+    assert_eq!(
+        snapshots[3].moved_box,
+        Some((Vector2::new(6, 2), Vector2::new(5, 2)))
+    );
+    assert!(snapshots[3].box_positions.contains(&Vector2::new(5, 2)));
+    assert_eq!(
+        snapshots
+            .iter()
+            .filter(|snapshot| snapshot.moved_box.is_some())
+            .count(),
+        actions.pushes()
+    );
+    assert_eq!(
+        &snapshots.last().unwrap().box_positions,
+        map.goal_positions()
+    );
+
+    let actions = Actions::from_str("ruuLLLLLLrr").unwrap();
+    let mut replay = map.replay(&actions);
+    assert_eq!(replay.by_ref().filter(Result::is_ok).count(), 8);
+    assert_eq!(replay.next(), None);
+    assert_eq!(
+        map.replay(&actions).last(),
+        Some(Err(VerifyError::PushBlocked(8)))
+    );
+}
+
 #[test]
 fn get() {
     let mut map: Map = load_level_from_file("assets/Holland_81.xsb", 9).into();
@@ -103,6 +219,115 @@ fn get() {
     }
 }
 
+#[test]
+fn edit() {
+    let mut map = Map::with_dimensions(Vector2::new(3, 1));
+    assert_eq!(
+        map.validate(),
+        [ValidationError::NoPlayer, ValidationError::NoBoxOrGoal]
+    );
+    map.resize(Vector2::new(5, 3), Vector2::new(1, 1)).unwrap();
+    for x in 0..5 {
+        map.set_wall(Vector2::new(x, 0)).unwrap();
+        map.set_wall(Vector2::new(x, 2)).unwrap();
+    }
+    map.set_wall(Vector2::new(0, 1)).unwrap();
+    map.set_wall(Vector2::new(4, 1)).unwrap();
+    map.place_player(Vector2::new(1, 1)).unwrap();
+    map.add_box(Vector2::new(2, 1)).unwrap();
+    map.add_goal(Vector2::new(3, 1)).unwrap();
+    assert!(map.validate().is_empty());
+
+    // Tiles outside the walls are cleared
+    map.resize(Vector2::new(7, 5), Vector2::new(1, 1)).unwrap();
+    map.set_wall(Vector2::new(0, 0)).unwrap();
+    map.add_goal(Vector2::new(6, 4)).unwrap();
+    assert_eq!(map.validate(), [ValidationError::BoxGoalMismatch]);
+    map.fill_exterior();
+    assert!(map.validate().is_empty());
+    assert_eq!(
+        map.to_string(),
+        indoc! {"
+            -------
+            -#####-
+            -#@$.#-
+            -#####-
+            -------
+        "}
+    );
+    map.shrink_to_fit();
+
+    assert_eq!(map.add_box(Vector2::new(0, 1)), Err(EditError::Occupied));
+    assert_eq!(map.add_box(Vector2::new(5, 1)), Err(EditError::OutOfBounds));
+    assert_eq!(map.set_wall(Vector2::new(1, 1)), Err(EditError::Occupied));
+    assert_eq!(map.remove_box(Vector2::new(3, 1)), Err(EditError::NoBox));
+    assert_eq!(map.remove_goal(Vector2::new(2, 1)), Err(EditError::NoGoal));
+    assert_eq!(
+        map.resize(Vector2::new(3, 3), Vector2::new(-2, 0)),
+        Err(EditError::OutOfBounds)
+    );
+
+    // Walls remove boxes and update the reachable floors
+    map.set_wall(Vector2::new(2, 1)).unwrap();
+    assert!(map.box_positions().is_empty());
+    assert!(!map[Vector2::new(3, 1)].intersects(Tiles::Floor));
+    assert_eq!(
+        map.validate(),
+        [
+            ValidationError::BoxGoalMismatch,
+            ValidationError::NoBoxOrGoal
+        ]
+    );
+    map.remove_wall(Vector2::new(2, 1)).unwrap();
+    assert!(map[Vector2::new(3, 1)].intersects(Tiles::Floor));
+    map.add_box(Vector2::new(2, 1)).unwrap();
+    map.place_player(Vector2::new(3, 1)).unwrap();
+    assert_eq!(map.player_position(), Vector2::new(3, 1));
+    assert_eq!(map.to_string(), "#####\n#_$+#\n#####\n");
+}
+
+#[test]
+fn validate() {
+    let map: Map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    assert!(map.validate().is_empty());
+
+    let map = Map::from_str("#####\n#@$.\n#####").unwrap();
+    assert_eq!(
+        map.validate(),
+        [ValidationError::OpenBorder(Vector2::new(4, 1))]
+    );
+
+    let map = Map::from_str(indoc! {"
+        ########
+        #@$.# $#
+        #  .#  #
+        ########
+    "})
+    .unwrap();
+    assert_eq!(
+        map.validate(),
+        [ValidationError::UnreachableBox(Vector2::new(6, 1))]
+    );
+
+    let map = Map::from_str(indoc! {"
+        #######
+        #$ @ .#
+        #  $ .#
+        # #####
+        ###
+    "})
+    .unwrap();
+    assert_eq!(
+        map.validate(),
+        [
+            ValidationError::StaticDeadlock(Vector2::new(1, 1)),
+            ValidationError::ImmovableBox(Vector2::new(1, 1)),
+            ValidationError::GoalUnreachableByBoxes(Vector2::new(5, 1)),
+            ValidationError::UselessFloor(Vector2::new(1, 3)),
+        ]
+    );
+}
+
 #[test]
 fn display() {
     let map = load_level_from_file("assets/Holland_81.xsb", 9)
@@ -261,6 +486,51 @@ fn normalize() {
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn canonical_form() {
+    let map = load_level_from_file("assets/Sasquatch_50.xsb", 41)
+        .map()
+        .clone();
+    let canonical_form = map.canonical_form();
+    let fingerprint = map.fingerprint();
+
+    let mut transformed_map = map.clone();
+    for i in 0..8 {
+        if i == 4 {
+            transformed_map.flip();
+        }
+        transformed_map.rotate();
+        assert_eq!(transformed_map.canonical_form(), canonical_form);
+        assert_eq!(transformed_map.fingerprint(), fingerprint);
+    }
+
+    let mut oversize_map = Map::from_str(
+        r#"
+        ---------------
+        ----####-------
+        --###  ####----
+        --#     $ #----
+        --# #  #$ #----
+        --# . .#@ #----
+        --#########----
+        ---------------
+    "#,
+    )
+    .unwrap();
+    oversize_map.flip();
+    let map = load_level_from_file("assets/Microban_155.xsb", 3)
+        .map()
+        .clone();
+    assert_eq!(oversize_map.fingerprint(), map.fingerprint());
+    // Fingerprints must stay stable across toolchains.
+    assert_eq!(map.fingerprint(), 1026995839280898034);
+
+    let other_map = load_level_from_file("assets/Microban_155.xsb", 4)
+        .map()
+        .clone();
+    assert_ne!(other_map.fingerprint(), map.fingerprint());
+}
+
 #[test]
 fn trimmed() {
     let mut oversize_map = Map::from_str(
@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use nalgebra::Vector2;
+use soukoban::{
+    direction::Direction, multiban::*, Action, ActionError, Map, ParseActionsError, ParseMapError,
+    SearchError,
+};
+
+const LEVEL: &str = "#########
+#@$.#.$@#
+#########
+";
+
+#[test]
+fn parse() {
+    assert_eq!(
+        Map::from_str(LEVEL).unwrap_err(),
+        ParseMapError::MoreThanOnePlayer
+    );
+    let map = MultiMap::from_str(LEVEL).unwrap();
+    assert_eq!(
+        map.player_positions(),
+        [Vector2::new(1, 1), Vector2::new(7, 1)]
+    );
+    assert_eq!(map.box_positions().len(), 2);
+    // The rooms of both players are floors
+    assert!(map[Vector2::new(3, 1)].intersects(soukoban::Tiles::Floor));
+    assert!(map[Vector2::new(5, 1)].intersects(soukoban::Tiles::Floor));
+    assert_eq!(
+        MultiMap::from_str("#####\n#$. #\n#####").unwrap_err(),
+        ParseMapError::NoPlayer
+    );
+    // The map is parsed by the parser of `Map`, so RLE is supported
+    assert_eq!(MultiMap::from_str("9#|#@$.#.$@#|9#").unwrap(), map);
+}
+
+#[test]
+fn actions() {
+    let actions = MultiActions::from_str("rR[2]3l[1]U").unwrap();
+    assert_eq!(actions.moves(), 6);
+    assert_eq!(actions.pushes(), 2);
+    assert_eq!(
+        actions[2],
+        MultiAction {
+            player: 1,
+            action: Action::Move(Direction::Left)
+        }
+    );
+    assert_eq!(actions.of_player(0).to_string(), "rRU");
+    assert_eq!(actions.to_string(), "rR[2]lll[1]U");
+
+    for lurd in ["r[0]l", "r[2l", "r[x]l"] {
+        assert_eq!(
+            MultiActions::from_str(lurd).unwrap_err(),
+            ParseActionsError::InvalidPlayerMarker
+        );
+    }
+}
+
+#[test]
+fn do_action() {
+    let mut level = MultiLevel::from_str("######\n#@@$.#\n######").unwrap();
+    // Players block each other
+    assert_eq!(
+        level.do_action(0, Direction::Right),
+        Err(ActionError::MoveBlocked)
+    );
+    assert_eq!(
+        level.do_action(2, Direction::Right),
+        Err(ActionError::InvalidPlayer(2))
+    );
+    level.do_action(1, Direction::Right).unwrap();
+    assert!(level.is_solved());
+    level.do_action(0, Direction::Right).unwrap();
+    assert_eq!(level.actions().to_string(), "[2]R[1]r");
+
+    level.undo_action().unwrap();
+    level.undo_action().unwrap();
+    assert!(!level.is_solved());
+    assert_eq!(
+        level.map().player_positions(),
+        [Vector2::new(1, 1), Vector2::new(2, 1)]
+    );
+    assert_eq!(level.undo_action(), Err(ActionError::NoActions));
+
+    level.redo_action().unwrap();
+    level.redo_action().unwrap();
+    assert!(level.is_solved());
+    assert_eq!(level.actions().to_string(), "[2]R[1]r");
+    assert_eq!(level.redo_action(), Err(ActionError::NoUndoneActions));
+}
+
+#[test]
+fn undo_tree() {
+    let mut level = MultiLevel::from_str(LEVEL).unwrap();
+    level.do_action(0, Direction::Right).unwrap();
+    level.undo_action().unwrap();
+    // Performing a new action after undoing creates a new branch
+    level.do_action(1, Direction::Left).unwrap();
+    level.undo_action().unwrap();
+    assert_eq!(level.undo_tree().branches().len(), 2);
+    assert_eq!(level.undo_tree().active_branch(), Some(1));
+
+    level.switch_branch(0).unwrap();
+    level.redo_action().unwrap();
+    assert_eq!(level.actions().to_string(), "R");
+    assert_eq!(
+        level.map().player_positions(),
+        [Vector2::new(2, 1), Vector2::new(7, 1)]
+    );
+    let lines: Vec<_> = level
+        .undo_tree()
+        .lines()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(lines, ["R", "[2]L"]);
+    assert_eq!(level.switch_branch(1), Err(ActionError::NoBranch));
+}
+
+#[test]
+fn solver() {
+    let solver = MultiSolver::new(MultiMap::from_str(LEVEL).unwrap());
+    let solution = solver.a_star_search().unwrap();
+    assert_eq!(solution.moves(), 2);
+    assert_eq!(solution.pushes(), 2);
+    let mut level = MultiLevel::from_map(solver.map().clone());
+    level.do_actions(solution.iter()).unwrap();
+    assert!(level.is_solved());
+
+    // Only the second player can push the box
+    let solver = MultiSolver::new(MultiMap::from_str("######\n#@@$.#\n######").unwrap());
+    assert_eq!(solver.a_star_search().unwrap().to_string(), "[2]R");
+
+    let mut solver = MultiSolver::new(MultiMap::from_str(LEVEL).unwrap());
+    solver.set_node_limit(Some(0));
+    assert_eq!(solver.a_star_search(), Err(SearchError::LimitExceeded));
+}
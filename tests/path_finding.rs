@@ -1,7 +1,15 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use nalgebra::Vector2;
-use soukoban::path_finding::*;
+use soukoban::{
+    error::SearchError,
+    path_finding::*,
+    solver::{Solver, Strategy},
+    Actions, Map, Tiles,
+};
 
 mod utils;
 use utils::*;
@@ -60,3 +68,155 @@ fn test_pushable_boxes() {
     let map = load_level_from_file("assets/Microban_155.xsb", 3).into();
     assert_eq!(pushable_boxes(&map), HashSet::from([Vector2::new(6, 2)]));
 }
+
+#[test]
+fn test_box_move_actions() {
+    let map: Map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    assert_eq!(
+        box_move_actions(
+            &map,
+            Vector2::new(6, 2),
+            Vector2::new(6, 2),
+            Optimization::Moves
+        ),
+        Some(Actions::new())
+    );
+    assert_eq!(
+        box_move_actions(
+            &map,
+            Vector2::new(6, 3),
+            Vector2::new(6, 4),
+            Optimization::Moves
+        ),
+        None
+    );
+    let actions = box_move_actions(
+        &map,
+        Vector2::new(6, 2),
+        Vector2::new(2, 4),
+        Optimization::Moves,
+    )
+    .unwrap();
+    assert_eq!(actions, Actions::from_str("ruuLLLulDDrdL").unwrap());
+
+    let map: Map = load_level_from_file("assets/Microban II_135.xsb", 132).into();
+    let actions = box_move_actions(
+        &map,
+        Vector2::new(8, 7),
+        Vector2::new(9, 8),
+        Optimization::Moves,
+    )
+    .unwrap();
+    assert_eq!(actions.moves(), 487);
+    assert_eq!(actions.pushes(), 110);
+    let snapshot = map.replay(&actions).last().unwrap().unwrap();
+    assert!(snapshot.box_positions.contains(&Vector2::new(9, 8)));
+
+    let map: Map = load_level_from_file("assets/Microban II_135.xsb", 133).into();
+    let actions = box_move_actions(
+        &map,
+        Vector2::new(18, 18),
+        Vector2::new(17, 18),
+        Optimization::Moves,
+    )
+    .unwrap();
+    assert_eq!(actions.moves(), 618);
+    assert_eq!(actions.pushes(), 11);
+
+    let map: Map = load_level_from_file("assets/Microban_155.xsb", 22).into();
+    let (from, to) = (Vector2::new(4, 5), Vector2::new(1, 3));
+    let actions = box_move_actions(&map, from, to, Optimization::Moves).unwrap();
+    assert_eq!(actions.to_string(), "drrruulDrdLLdlUUUUruLulD");
+    let actions = box_move_actions(&map, from, to, Optimization::Pushes).unwrap();
+    assert_eq!(actions.to_string(), "drrruulDrdLLdlUUUddrruuruulldL");
+}
+
+#[test]
+fn test_boxes_move_actions() {
+    let map = Map::from_str(
+        "#######\n\
+         #  .  #\n\
+         # @$$ #\n\
+         #  .  #\n\
+         #######",
+    )
+    .unwrap();
+    assert_eq!(
+        box_move_actions(
+            &map,
+            Vector2::new(3, 2),
+            Vector2::new(5, 2),
+            Optimization::Moves
+        ),
+        None
+    );
+    let targets = HashMap::from([(Vector2::new(3, 2), Vector2::new(5, 2))]);
+    let actions = boxes_move_actions(&map, &targets, 1000).unwrap();
+    assert_eq!(actions.pushes(), 3);
+    let snapshot = map.replay(&actions).last().unwrap().unwrap();
+    assert!(snapshot.box_positions.contains(&Vector2::new(5, 2)));
+    assert_eq!(
+        boxes_move_actions(&map, &targets, 0),
+        Err(SearchError::LimitExceeded)
+    );
+
+    let targets = HashMap::from([
+        (Vector2::new(3, 2), Vector2::new(3, 1)),
+        (Vector2::new(4, 2), Vector2::new(3, 3)),
+    ]);
+    let actions = boxes_move_actions(&map, &targets, 1000).unwrap();
+    let snapshot = map.replay(&actions).last().unwrap().unwrap();
+    assert_eq!(
+        snapshot.box_positions,
+        HashSet::from([Vector2::new(3, 1), Vector2::new(3, 3)])
+    );
+
+    let targets = HashMap::from([(Vector2::new(3, 2), Vector2::new(0, 2))]);
+    assert_eq!(
+        boxes_move_actions(&map, &targets, 1000),
+        Err(SearchError::NoSolution)
+    );
+}
+
+#[test]
+fn distance_table() {
+    let map = load_level_from_file("assets/Microban II_135.xsb", 132).into();
+    let table = DistanceTable::new(&map);
+    let floors: Vec<_> = (0..map.dimensions().y)
+        .flat_map(|y| (0..map.dimensions().x).map(move |x| Vector2::new(x, y)))
+        .filter(|&position| map[position].intersects(Tiles::Floor))
+        .collect();
+    for &from in floors.iter().step_by(7) {
+        for &to in &floors {
+            let path = soukoban::path_finding::find_path(from, to, |position| {
+                map[position].intersects(Tiles::Floor)
+            });
+            assert_eq!(
+                table.distance(from, to),
+                path.map(|path| path.len() as i32 - 1)
+            );
+        }
+    }
+    assert_eq!(table.distance(Vector2::new(0, 0), floors[0]), None);
+}
+
+#[test]
+fn push_distance_table() {
+    let map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    let table = PushDistanceTable::new(&map);
+    let solver = Solver::new(map, Strategy::Fast);
+    for (&box_position, &lower_bound) in solver.lower_bounds() {
+        assert_eq!(table.minimum_distance(box_position), Some(lower_bound));
+    }
+    assert_eq!(table.minimum_distance(Vector2::new(5, 2)), Some(5));
+    assert_eq!(
+        table.distance(Vector2::new(6, 2), Vector2::new(4, 4)),
+        Some(6)
+    );
+    assert_eq!(
+        table.distance(Vector2::new(6, 3), Vector2::new(2, 4)),
+        Some(7)
+    );
+    assert_eq!(table.distance(Vector2::new(4, 4), Vector2::new(2, 4)), None);
+    assert_eq!(table.minimum_distance(Vector2::new(7, 2)), None);
+}
@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use nalgebra::Vector2;
+use soukoban::{path_finding::find_path, render::*, Actions, Map, VerifyError};
+
+mod utils;
+use utils::*;
+
+#[test]
+fn svg() {
+    let map: Map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    let svg = render_svg(&map, &SvgOptions::default());
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="288" height="192""#));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches(r#"class="box""#).count(), 2);
+    assert_eq!(svg.matches(r#"class="goal""#).count(), 2);
+    assert_eq!(svg.matches(r#"class="player""#).count(), 1);
+    assert!(!svg.contains(r#"class="deadlock""#));
+
+    let options = SvgOptions {
+        cell_size: 16,
+        theme: Theme::dark(),
+        overlays: vec![
+            Overlay::StaticDeadlocks,
+            Overlay::LowerBounds,
+            Overlay::ReachableArea,
+            Overlay::Tunnels,
+            Overlay::Path(vec![Vector2::new(6, 4), Vector2::new(7, 4)]),
+        ],
+        ..Default::default()
+    };
+    let svg = render_svg(&map, &options);
+    assert!(svg.contains(r#"width="144" height="96""#));
+    assert!(svg.contains(&Theme::dark().background));
+    assert!(svg.contains(r#"class="deadlock""#));
+    assert!(svg.contains(r#"class="lower-bound""#));
+    assert!(svg.contains(r#"class="reachable""#));
+    assert!(svg.contains(r#"<polyline class="path" points="104,72 120,72""#));
+}
+
+#[test]
+fn svg_solution() {
+    let map: Map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    let solution = Actions::from_str("ruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurDD").unwrap();
+    let options = SvgOptions::default();
+
+    let frames = render_svg_frames(&map, &solution, &options).unwrap();
+    assert_eq!(frames.len(), solution.len() + 1);
+    assert_eq!(frames[0], render_svg(&map, &options));
+    assert_eq!(
+        frames
+            .last()
+            .unwrap()
+            .matches(&options.theme.box_on_goal)
+            .count(),
+        2
+    );
+
+    let animation = render_svg_animation(&map, &solution, &options).unwrap();
+    assert_eq!(animation.matches("<set ").count(), solution.len() + 1);
+    assert_eq!(animation.matches(r#"fill="freeze""#).count(), 1);
+
+    let actions = Actions::from_str("rr").unwrap();
+    assert_eq!(
+        render_svg_frames(&map, &actions, &options),
+        Err(VerifyError::MoveBlocked(1))
+    );
+    assert_eq!(
+        render_svg_animation(&map, &actions, &options),
+        Err(VerifyError::MoveBlocked(1))
+    );
+}
+
+#[test]
+fn terminal() {
+    let map: Map = load_level_from_file("assets/Microban_155.xsb", 3).into();
+    let options = TerminalOptions {
+        colors: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        render_terminal(&map, &options),
+        "    ████████      \n\
+         ██████    ████████\n\
+         ██          ■   ██\n\
+         ██  ██    ██■   ██\n\
+         ██  ·   · ██●   ██\n\
+         ██████████████████\n"
+    );
+
+    let path = find_path(map.player_position(), Vector2::new(7, 2), |position| {
+        map.can_move(position)
+    })
+    .unwrap();
+    let options = TerminalOptions {
+        colors: false,
+        overlays: vec![
+            Overlay::StaticDeadlocks,
+            Overlay::LowerBounds,
+            Overlay::Path(path),
+        ],
+    };
+    assert_eq!(
+        render_terminal(&map, &options),
+        "    ████████      \n\
+         ██████✕ ✕ ████████\n\
+         ██✕  4 3 2 5■ ∘ ██\n\
+         ██✕ ██ 2 1██■ ∘ ██\n\
+         ██✕  0 1 0██● ∘ ██\n\
+         ██████████████████\n"
+    );
+
+    let output = render_terminal(&map, &TerminalOptions::default());
+    assert!(output.contains("\x1b[38;5;178m■ \x1b[0m"));
+    assert_eq!(output.lines().count(), 6);
+
+    // Lower bounds above 99 keep the columns aligned
+    let corridor = format!(
+        "{}\n#.{}$@#\n{}",
+        "#".repeat(106),
+        " ".repeat(100),
+        "#".repeat(106)
+    );
+    let map = Map::from_str(&corridor).unwrap();
+    let options = TerminalOptions {
+        colors: false,
+        overlays: vec![Overlay::LowerBounds],
+    };
+    let output = render_terminal(&map, &options);
+    assert!(output.contains("99"));
+    assert!(output
+        .lines()
+        .all(|line| line.chars().count() == 2 * map.dimensions().x as usize));
+}
@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use nalgebra::Vector2;
+use soukoban::{
+    direction::Direction, reverse_level::ReverseLevel, rules::MovementRules, solver::Solver,
+    ActionError, Actions, Map,
+};
+
+mod utils;
+use utils::*;
+
+#[test]
+fn reverse_level() {
+    // Microban #3
+    let map = load_level_from_file("assets/Microban_155.xsb", 3)
+        .map()
+        .clone();
+    let solution = Actions::from_str("ruuLLLLrrrrddlUruLLLulDrddllluuRRDrdLuuurDD").unwrap();
+    let final_player_position = map
+        .replay(&solution)
+        .last()
+        .unwrap()
+        .unwrap()
+        .player_position;
+
+    let mut level = ReverseLevel::from_map(map.clone()).unwrap();
+    assert_eq!(level.map().box_positions(), map.goal_positions());
+    assert_eq!(level.do_pull(Direction::Up), Err(ActionError::NoBoxToPull));
+    assert_eq!(
+        level.jump(Vector2::new(0, 0)),
+        Err(ActionError::MoveBlocked)
+    );
+    let initial_player_position = level.map().player_position();
+    level.jump(final_player_position).unwrap();
+    level.undo_action().unwrap();
+    assert_eq!(level.map().player_position(), initial_player_position);
+    assert_eq!(level.undo_action(), Err(ActionError::NoActions));
+    level.jump(final_player_position).unwrap();
+    let reverse_map = level.map().clone();
+    for action in solution.iter().rev() {
+        if action.is_push() {
+            level.do_pull(-action.direction()).unwrap();
+        } else {
+            level.do_move(-action.direction()).unwrap();
+        }
+    }
+    assert_eq!(
+        level.jump(map.player_position()),
+        Err(ActionError::JumpNotAllowed)
+    );
+    assert!(level.is_solved());
+
+    // Pulls are recorded as pulls, so the reverse actions can be replayed
+    assert_eq!(level.actions().pulls(), solution.pushes());
+    assert_eq!(level.actions().pushes(), 0);
+    assert!(reverse_map
+        .replay_with_rules(level.actions(), MovementRules::PullOnly)
+        .all(|state| state.is_ok()));
+    assert!(reverse_map
+        .replay(level.actions())
+        .any(|state| state.is_err()));
+
+    let forward_actions = level.forward_actions().unwrap();
+    assert_eq!(forward_actions, solution);
+    assert!(map.verify(&forward_actions).is_ok());
+
+    while level
+        .actions()
+        .last()
+        .is_some_and(|action| action.is_move())
+    {
+        level.undo_action().unwrap();
+    }
+    level.undo_action().unwrap();
+    assert!(!level.is_solved());
+    assert_eq!(level.forward_actions(), None);
+}
+
+#[test]
+fn forward_actions() {
+    let map = load_level_from_file("assets/Microban_155.xsb", 1)
+        .map()
+        .clone();
+    let mut level = ReverseLevel::from_map(map.clone()).unwrap();
+    let solution = Solver::new(map.clone(), Default::default())
+        .a_star_search()
+        .unwrap();
+    let final_player_position = map
+        .replay(&solution)
+        .last()
+        .unwrap()
+        .unwrap()
+        .player_position;
+    level.jump(final_player_position).unwrap();
+    for action in solution.iter().rev() {
+        if action.is_push() {
+            level.do_pull(-action.direction()).unwrap();
+        } else {
+            level.do_move(-action.direction()).unwrap();
+        }
+    }
+    // The player does not have to return to its initial position
+    while level
+        .actions()
+        .last()
+        .is_some_and(|action| action.is_move())
+    {
+        level.undo_action().unwrap();
+    }
+    assert!(level.is_solved());
+    assert!(map.verify(&level.forward_actions().unwrap()).is_ok());
+}
+
+#[test]
+fn no_free_floor() {
+    let map = Map::from_str(
+        r#"
+        #####
+        #+#$#
+        #####
+    "#,
+    )
+    .unwrap();
+    assert_eq!(ReverseLevel::from_map(map), Err(ActionError::NoFreeFloor));
+}
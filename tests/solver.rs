@@ -1,5 +1,11 @@
-use nalgebra::Vector2;
-use soukoban::{solver::*, Level};
+use std::{str::FromStr, time::Duration};
+
+use soukoban::{
+    render::{render_terminal, Overlay, TerminalOptions},
+    rules::MovementRules,
+    solver::*,
+    Level, Map, SearchError,
+};
 
 mod utils;
 use utils::*;
@@ -21,17 +27,99 @@ fn test_solver() {
     solve(load_level_from_file("assets/BoxWorld_100.xsb", 3));
 }
 
+#[test]
+fn node_limit() {
+    let map = load_level_from_file("assets/Microban_155.xsb", 1).into();
+    let mut solver = Solver::new(map, Strategy::Fast);
+    solver.set_node_limit(Some(10));
+    assert_eq!(solver.a_star_search(), Err(SearchError::LimitExceeded));
+    assert_eq!(solver.expanded_nodes(), 10);
+    solver.set_node_limit(None);
+    assert!(solver.a_star_search().is_ok());
+    assert_eq!(solver.expanded_nodes(), 15);
+    assert!(solver.generated_nodes() >= solver.expanded_nodes());
+}
+
+#[test]
+fn time_limit() {
+    let map = load_level_from_file("assets/Microban_155.xsb", 1).into();
+    let mut solver = Solver::new(map, Strategy::Fast);
+    solver.set_time_limit(Some(Duration::ZERO));
+    assert_eq!(solver.a_star_search(), Err(SearchError::LimitExceeded));
+    assert_eq!(solver.expanded_nodes(), 0);
+    solver.set_time_limit(Some(Duration::from_secs(60)));
+    assert!(solver.a_star_search().is_ok());
+}
+
+#[test]
+fn sokoban_plus() {
+    let xsb = "#######|#     #|# $ $ #|#. @ .#|#######";
+    let plain = Level::from_str(xsb).unwrap();
+    let solver = Solver::new(plain.map().clone(), Strategy::OptimalPush);
+    let plain_pushes = solver.a_star_search().unwrap().pushes();
+
+    // The boxes must be pushed to the goals on the opposite sides
+    let mut level =
+        Level::from_str(&format!("{xsb}\nBoxSequence: 1 2\nGoalSequence: 2 1")).unwrap();
+    let solver = Solver::new(level.map().clone(), Strategy::OptimalPush);
+    let solution = solver.a_star_search().unwrap();
+    assert!(solution.pushes() > plain_pushes);
+    assert!(level.map().verify(&solution).is_ok());
+    level
+        .do_actions(solution.iter().map(|action| action.direction()))
+        .unwrap();
+    assert!(level.is_solved());
+
+    // The box in the corner can not reach the goal with the same ID
+    let level = Level::from_str("######|#*  .#|# $@ #|######\nBoxSequence: 1 2\nGoalSequence: 2 1")
+        .unwrap();
+    let solver = Solver::new(level.map().clone(), Strategy::Fast);
+    assert_eq!(solver.a_star_search(), Err(SearchError::NoSolution));
+}
+
+#[test]
+fn movement_rules() {
+    let map = Map::from_str("#######|#     #|# .$ @#|#######").unwrap();
+    let mut solver = Solver::new(map.clone(), Strategy::OptimalPush);
+    solver.set_movement_rules(MovementRules::PullOnly);
+    let solution = solver.a_star_search().unwrap();
+    assert_eq!((solution.moves(), solution.pulls()), (6, 1));
+    assert!(map
+        .verify_with_rules(&solution, MovementRules::PullOnly)
+        .is_ok());
+
+    // The box can only leave the wall by being pulled
+    let map = Map::from_str("######|#    #|#   .#|#@$  #|######").unwrap();
+    let mut solver = Solver::new(map.clone(), Strategy::OptimalPush);
+    solver.set_movement_rules(MovementRules::PushAndPull);
+    let solution = solver.a_star_search().unwrap();
+    assert_eq!(solution.pushes() + solution.pulls(), 3);
+    assert!(solution.pulls() > 0);
+    assert!(map
+        .verify_with_rules(&solution, MovementRules::PushAndPull)
+        .is_ok());
+}
+
+#[test]
+fn grid_solver() {
+    // The topology-agnostic solver also runs on square maps
+    for id in 1..=3 {
+        let mut level = load_level_from_file("assets/Microban_155.xsb", id);
+        let solver = Solver::new(level.map().clone(), Strategy::OptimalPush);
+        let pushes = solver.a_star_search().unwrap().pushes();
+
+        let solver = GridSolver::new(level.map().clone());
+        level.do_actions(solver.a_star_search().unwrap()).unwrap();
+        assert!(level.is_solved());
+        assert_eq!(level.actions().pushes(), pushes);
+    }
+}
+
 #[expect(dead_code)]
 fn print_lower_bounds(solver: &Solver) {
-    for y in 0..solver.map().dimensions().y {
-        for x in 0..solver.map().dimensions().x {
-            let position = Vector2::new(x, y);
-            if let Some(lower_bound) = solver.lower_bounds().get(&position) {
-                print!("{lower_bound:3} ");
-            } else {
-                print!("{:3} ", "###");
-            }
-        }
-        println!();
-    }
+    let options = TerminalOptions {
+        overlays: vec![Overlay::LowerBounds],
+        ..Default::default()
+    };
+    print!("{}", render_terminal(solver.map(), &options));
 }
@@ -0,0 +1,158 @@
+use std::str::FromStr;
+
+use nalgebra::Vector2;
+use soukoban::{
+    path_finding::{find_path_in, reachable_area_in},
+    topology::{Grid, Topology, Triangular},
+    trioban::*,
+    ActionError, SearchError,
+};
+
+const LEVEL: &str = "#########
+#       #
+# @$ .  #
+#       #
+#########
+";
+
+#[test]
+fn parse() {
+    let map = TriMap::from_str(LEVEL).unwrap();
+    assert_eq!(map.dimensions(), Vector2::new(9, 5));
+    assert_eq!(map.player_position(), Vector2::new(2, 2));
+    assert_eq!(map.to_string(), LEVEL);
+
+    // The orientation of the triangles is kept when the indentation is odd
+    let indented: String = LEVEL.lines().map(|line| format!(" {line}\n")).collect();
+    let map = TriMap::from_str(&indented).unwrap();
+    assert_eq!(map.player_position(), Vector2::new(3, 2));
+    assert_eq!(map.to_string(), indented);
+
+    // The indentation is measured after splitting rows and decoding RLE
+    let map = TriMap::from_str("3 9#|3 #7 #|3 # @$ .2 #|3 #7 #|3 9#").unwrap();
+    assert_eq!(map.player_position(), Vector2::new(3, 2));
+    assert_eq!(map.to_string(), indented);
+}
+
+#[test]
+fn topology() {
+    // The triangle at (2, 2) points up, and the triangle at (3, 2) points down
+    let up = Vector2::new(2, 2);
+    let down = Vector2::new(3, 2);
+    assert_eq!(
+        Triangular::neighbor(up, TriDirection::SouthEast),
+        Vector2::new(2, 3)
+    );
+    assert_eq!(Triangular::neighbor(up, TriDirection::NorthEast), down);
+    assert_eq!(
+        Triangular::neighbor(down, TriDirection::NorthEast),
+        Vector2::new(3, 1)
+    );
+    assert_eq!(
+        Triangular::neighbor(down, TriDirection::SouthEast),
+        Vector2::new(4, 2)
+    );
+    for direction in TriDirection::iter() {
+        for position in [up, down] {
+            let neighbor = Triangular::neighbor(position, direction);
+            assert_eq!(Triangular::neighbor(neighbor, -direction), position);
+        }
+    }
+}
+
+#[test]
+fn do_move() {
+    let mut map = TriMap::from_str(LEVEL).unwrap();
+    // The box moves along the 60° strip
+    assert_eq!(map.do_move(TriDirection::NorthEast), Ok(true));
+    assert!(map.box_positions().contains(&Vector2::new(3, 1)));
+    assert_eq!(map.do_move(TriDirection::NorthEast), Ok(true));
+    assert!(map.box_positions().contains(&Vector2::new(4, 1)));
+    assert_eq!(
+        map.do_move(TriDirection::NorthEast),
+        Err(ActionError::PushBlocked)
+    );
+    assert_eq!(map.do_move(TriDirection::West), Ok(false));
+    assert_eq!(map.do_move(TriDirection::West), Ok(false));
+    assert_eq!(map.player_position(), Vector2::new(1, 1));
+    assert_eq!(
+        map.do_move(TriDirection::West),
+        Err(ActionError::MoveBlocked)
+    );
+}
+
+#[test]
+fn level() {
+    let mut level = TriLevel::from_str(LEVEL).unwrap();
+    assert_eq!(
+        level.do_action(TriDirection::NorthEast),
+        Ok(TriAction::Push(TriDirection::NorthEast))
+    );
+    assert_eq!(
+        level.do_action(TriDirection::West),
+        Ok(TriAction::Move(TriDirection::West))
+    );
+    level.do_action(TriDirection::West).unwrap();
+    assert_eq!(
+        level.do_action(TriDirection::West),
+        Err(ActionError::MoveBlocked)
+    );
+    assert_eq!(level.actions().len(), 3);
+
+    let map = level.map().clone();
+    for _ in 0..3 {
+        level.undo_action().unwrap();
+    }
+    assert_eq!(level.undo_action(), Err(ActionError::NoActions));
+    assert_eq!(level.map(), &TriMap::from_str(LEVEL).unwrap());
+    assert!(level.actions().is_empty());
+
+    for _ in 0..3 {
+        level.redo_action().unwrap();
+    }
+    assert_eq!(level.redo_action(), Err(ActionError::NoUndoneActions));
+    assert_eq!(level.map(), &map);
+
+    // New actions discard the undone actions
+    level.undo_action().unwrap();
+    level.do_action(TriDirection::East).unwrap();
+    assert_eq!(level.redo_action(), Err(ActionError::NoUndoneActions));
+}
+
+#[test]
+fn path_finding() {
+    let map = TriMap::from_str(LEVEL).unwrap();
+    let area =
+        reachable_area_in::<Triangular>(map.player_position(), |position| map.can_move(position));
+    assert_eq!(area.len(), 20);
+    let path = find_path_in::<Triangular>(map.player_position(), Vector2::new(4, 2), |position| {
+        map.can_move(position)
+    })
+    .unwrap();
+    assert_eq!(path.len() - 1, 4);
+}
+
+#[test]
+fn static_deadlocks() {
+    let map = TriMap::from_str(LEVEL).unwrap();
+    let deadlocks = calculate_static_deadlocks(&map);
+    assert_eq!(deadlocks, [Vector2::new(1, 3), Vector2::new(7, 3)].into());
+}
+
+#[test]
+fn solver() {
+    let map = TriMap::from_str(LEVEL).unwrap();
+    let solver = TriSolver::new(map.clone());
+    let solution = solver.a_star_search().unwrap();
+    assert_eq!(solution, [TriDirection::East, TriDirection::East]);
+
+    let mut replay = map;
+    for direction in solution {
+        replay.do_move(direction).unwrap();
+    }
+    assert!(replay.is_solved());
+
+    let mut solver = TriSolver::new(TriMap::from_str(LEVEL).unwrap());
+    solver.set_node_limit(Some(0));
+    assert_eq!(solver.a_star_search(), Err(SearchError::LimitExceeded));
+}